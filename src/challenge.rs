@@ -0,0 +1,36 @@
+use crate::puzzle::Puzzle;
+
+/// The fewest extra swaps a board allows on top of its par
+const MIN_SLACK: usize = 2;
+
+/// At difficulty 0 a board allows twice its par, and the extra swaps halve
+/// by this difficulty
+const SLACK_MIDPOINT: f64 = 20.0;
+
+/// How many swaps a challenge board may take, including undos and redos
+pub fn move_limit(puzzle: &Puzzle) -> usize {
+    limit_for(puzzle.par(), puzzle.settings.difficulty())
+}
+
+fn limit_for(par: usize, difficulty: usize) -> usize {
+    let slack = par as f64 / (1.0 + difficulty as f64 / SLACK_MIDPOINT);
+    par + (slack.ceil() as usize).max(MIN_SLACK)
+}
+
+/// A challenge board is failed once it runs out of moves without being solved
+pub fn is_failed(puzzle: &Puzzle) -> bool {
+    !puzzle.is_solved() && puzzle.stats.moves >= move_limit(puzzle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_tighten_with_difficulty() {
+        assert_eq!(limit_for(10, 0), 20);
+        assert_eq!(limit_for(10, 20), 15);
+        assert_eq!(limit_for(0, 5), MIN_SLACK);
+        assert_eq!(limit_for(10, 10_000), 10 + MIN_SLACK);
+    }
+}
@@ -0,0 +1,16 @@
+use stdweb::{js, unstable::TryInto};
+
+pub const MS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Milliseconds since the unix epoch
+pub fn now_ms() -> f64 {
+    js! { return Date.now(); }
+        .try_into()
+        .expect("Failed to get the current time")
+}
+
+/// Formats a duration as `m:ss`
+pub fn format_duration(ms: f64) -> String {
+    let seconds = (ms / 1000.0).max(0.0).round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,185 @@
+use crate::clock::{self, MS_PER_DAY};
+use crate::puzzle::{DifficultyProfile, Puzzle};
+use crate::savegame;
+use crate::stats;
+use serde::{Deserialize, Serialize};
+
+/// Daily puzzles are square so that everyone gets the same board no matter
+/// the shape of their window
+const DAILY_ASPECT: (usize, usize) = (1, 1);
+
+/// Difficulty of the daily puzzle for each weekday, starting on Monday
+const DIFFICULTY_SCHEDULE: [usize; 7] = [2, 5, 10, 16, 24, 36, 50];
+
+const WEEKDAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Results of the daily puzzles, kept separately from the main progression
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DailyRecord {
+    pub results: Vec<DailyResult>,
+    pub streak: usize,
+    pub best_streak: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyResult {
+    pub day: u64,
+    pub moves: usize,
+    pub par: usize,
+    pub stars: usize,
+    pub time_ms: f64,
+    /// The auto-lock assist helped with the solve
+    pub auto_locked: bool,
+}
+
+/// Days since the unix epoch in UTC
+pub fn today() -> u64 {
+    (clock::now_ms() / MS_PER_DAY).floor() as u64
+}
+
+/// Monday is 0, the unix epoch was on a Thursday
+fn weekday(day: u64) -> usize {
+    ((day + 3) % 7) as usize
+}
+
+pub fn difficulty(day: u64) -> usize {
+    DIFFICULTY_SCHEDULE[weekday(day)]
+}
+
+pub fn generate(day: u64) -> Puzzle {
+    Puzzle::generate(
+        DifficultyProfile::standard(),
+        difficulty(day),
+        day,
+        DAILY_ASPECT,
+    )
+}
+
+/// Converts days since the unix epoch into a (year, month, day) date
+// source: http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: u64) -> (i64, u32, u32) {
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+fn format_day(day: u64) -> String {
+    let (year, month, date) = civil_from_days(day);
+    format!(
+        "{}-{:02}-{:02} ({})",
+        year,
+        month,
+        date,
+        WEEKDAY_NAMES[weekday(day)]
+    )
+}
+
+impl DailyRecord {
+    pub fn load(key: &str) -> Self {
+        savegame::load(key).unwrap_or_default()
+    }
+
+    pub fn save(&self, key: &str) {
+        savegame::save(key, self);
+    }
+
+    pub fn result(&self, day: u64) -> Option<&DailyResult> {
+        self.results.iter().find(|result| result.day == day)
+    }
+
+    pub fn add_result(&mut self, result: DailyResult) {
+        if self.result(result.day).is_some() {
+            return;
+        }
+
+        let played_yesterday =
+            matches!(self.results.last(), Some(last) if last.day + 1 == result.day);
+        self.streak = if played_yesterday { self.streak + 1 } else { 1 };
+        self.best_streak = self.best_streak.max(self.streak);
+        self.results.push(result);
+    }
+
+    /// The streak is only kept alive if yesterday's or today's puzzle was solved
+    pub fn current_streak(&self, today: u64) -> usize {
+        match self.results.last() {
+            Some(last) if last.day + 1 >= today => self.streak,
+            _ => 0,
+        }
+    }
+}
+
+impl DailyResult {
+    pub fn from_puzzle(day: u64, puzzle: &Puzzle) -> Self {
+        Self {
+            day,
+            moves: puzzle.stats.moves,
+            par: puzzle.par(),
+            stars: puzzle.stars(),
+            time_ms: puzzle.stats.solve_time_ms().unwrap_or(0.0),
+            auto_locked: puzzle.stats.auto_locked,
+        }
+    }
+
+    /// A plain text summary of the result that can be shared
+    pub fn summary(&self, streak: usize) -> String {
+        format!(
+            "I Love Hue Daily {}\n{} {} moves (par {}) in {}{}\nStreak: {}",
+            format_day(self.day),
+            stats::format_stars(self.stars),
+            self.moves,
+            self.par,
+            clock::format_duration(self.time_ms),
+            if self.auto_locked {
+                " with auto-lock"
+            } else {
+                ""
+            },
+            streak,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(weekday(0), 3);
+        assert_eq!(civil_from_days(20_744), (2026, 10, 18));
+        assert_eq!(WEEKDAY_NAMES[weekday(20_744)], "Sun");
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn streaks() {
+        let result = |day| DailyResult {
+            day,
+            moves: 10,
+            par: 10,
+            stars: 3,
+            time_ms: 0.0,
+            auto_locked: false,
+        };
+
+        let mut record = DailyRecord::default();
+        record.add_result(result(10));
+        record.add_result(result(11));
+        record.add_result(result(11));
+        assert_eq!(record.streak, 2);
+        assert_eq!(record.current_streak(12), 2);
+        assert_eq!(record.current_streak(13), 0);
+
+        record.add_result(result(14));
+        assert_eq!(record.streak, 1);
+        assert_eq!(record.best_streak, 2);
+    }
+}
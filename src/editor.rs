@@ -0,0 +1,350 @@
+use crate::gradient::{Color, Gradient};
+use crate::puzzle::{
+    self, LockMask, LockingPattern, MoveKind, Puzzle, PuzzleSettings, SettingsError,
+};
+use crate::share_link;
+use yew::prelude::*;
+
+const MIN_SIZE: usize = 1;
+const MAX_SIZE: usize = 20;
+
+const CORNER_NAMES: [&str; 4] = ["Top Left", "Top Right", "Bottom Left", "Bottom Right"];
+
+/// Select value used for the painted lock mask
+const PAINTED: &str = "painted";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Space {
+    Rgb,
+    Hsl,
+}
+
+pub struct EditorView {
+    props: Props,
+    link: ComponentLink<Self>,
+    corners: [Color; 4],
+    width: usize,
+    height: usize,
+    space: Space,
+    locking_pattern: LockingPattern,
+    moves: MoveKind,
+    share_code: Option<String>,
+}
+
+pub enum Msg {
+    SetCorner(usize, String),
+    SetWidth(String),
+    SetHeight(String),
+    SetSpace(ChangeData),
+    SetLockingPattern(ChangeData),
+    SetMoves(ChangeData),
+    PaintCell(usize),
+    Play,
+    Export,
+    CopyLink,
+    Close,
+}
+
+#[derive(Clone, Properties)]
+pub struct Props {
+    #[props(required)]
+    pub onplay: Callback<Puzzle>,
+    #[props(required)]
+    pub onclose: Callback<()>,
+}
+
+impl Component for EditorView {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            corners: [
+                Color::rgb(255, 0, 0),
+                Color::rgb(255, 255, 0),
+                Color::rgb(0, 0, 255),
+                Color::rgb(0, 255, 0),
+            ],
+            width: 5,
+            height: 7,
+            space: Space::Rgb,
+            locking_pattern: LockingPattern::Corners,
+            moves: MoveKind::Swaps,
+            share_code: None,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        // any change invalidates the exported share code
+        self.share_code = None;
+        match msg {
+            Msg::SetCorner(corner, hex) => match Color::from_hex(&hex) {
+                Some(color) => self.corners[corner] = color,
+                None => return false,
+            },
+            Msg::SetWidth(value) => match parse_size(&value) {
+                Some(width) => self.resize(width, self.height),
+                None => return false,
+            },
+            Msg::SetHeight(value) => match parse_size(&value) {
+                Some(height) => self.resize(self.width, height),
+                None => return false,
+            },
+            Msg::SetSpace(ChangeData::Select(select)) => {
+                self.space = match select.value().as_deref() {
+                    Some("hsl") => Space::Hsl,
+                    _ => Space::Rgb,
+                }
+            }
+            Msg::SetLockingPattern(ChangeData::Select(select)) => {
+                let value = select.value().unwrap_or_default();
+                if value == PAINTED {
+                    // start painting from the pattern that was selected
+                    self.locking_pattern = LockingPattern::Custom(self.current_mask());
+                } else if let Some(pattern) = value
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| LockingPattern::PRESETS.get(index))
+                {
+                    self.locking_pattern = pattern.clone();
+                }
+            }
+            Msg::SetMoves(ChangeData::Select(select)) => {
+                if let Some(&moves) = select
+                    .value()
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .and_then(|index| MoveKind::ALL.get(index))
+                {
+                    self.moves = moves;
+                }
+            }
+            Msg::SetSpace(_) | Msg::SetLockingPattern(_) | Msg::SetMoves(_) => return false,
+            Msg::PaintCell(index) => {
+                let mut mask = self.current_mask();
+                mask.toggle(index % self.width, index / self.width);
+                self.locking_pattern = LockingPattern::Custom(mask);
+            }
+            Msg::Play => {
+                if let Ok(settings) = self.settings() {
+                    self.props.onplay.emit(Puzzle::from_settings(settings));
+                }
+                return false;
+            }
+            Msg::Export => {
+                if let Ok(settings) = self.settings() {
+                    self.share_code = Some(Puzzle::unshuffled(settings).share_code());
+                }
+            }
+            Msg::CopyLink => {
+                let settings = match self.settings() {
+                    Ok(settings) => settings,
+                    Err(_) => return false,
+                };
+                let code = Puzzle::unshuffled(settings).share_code();
+                share_link::copy_to_clipboard(&code);
+                self.share_code = Some(code);
+            }
+            Msg::Close => {
+                self.props.onclose.emit(());
+                return false;
+            }
+        }
+        true
+    }
+
+    fn view(&self) -> Html {
+        html! {
+            <div class="editor">
+                <div class="editor-panel">
+                    { for (0..4).map(|corner| self.corner_input(corner)) }
+                    <label>
+                        {"Width"}
+                        <input type="number" min=MIN_SIZE max=MAX_SIZE value=self.width
+                            oninput=self.link.callback(|data: InputData| Msg::SetWidth(data.value)) />
+                    </label>
+                    <label>
+                        {"Height"}
+                        <input type="number" min=MIN_SIZE max=MAX_SIZE value=self.height
+                            oninput=self.link.callback(|data: InputData| Msg::SetHeight(data.value)) />
+                    </label>
+                    <label>
+                        {"Blending"}
+                        <select onchange=self.link.callback(Msg::SetSpace)>
+                            <option value="rgb" selected=self.space == Space::Rgb>{"RGB"}</option>
+                            <option value="hsl" selected=self.space == Space::Hsl>{"HSL"}</option>
+                        </select>
+                    </label>
+                    { self.locking_pattern_select() }
+                    { self.moves_select() }
+                    <div class="editor-hint">{"Click on tiles to paint locks"}</div>
+                    { self.actions() }
+                </div>
+                { self.preview_grid() }
+            </div>
+        }
+    }
+}
+
+impl EditorView {
+    fn settings(&self) -> Result<PuzzleSettings, SettingsError> {
+        PuzzleSettings::custom(
+            self.width,
+            self.height,
+            self.gradient(),
+            self.locking_pattern.clone(),
+            self.moves,
+        )
+    }
+
+    fn gradient(&self) -> Gradient {
+        let corners = match self.space {
+            Space::Rgb => self.corners,
+            Space::Hsl => {
+                let [a, b, c, d] = self.corners;
+                [a.to_hsl(), b.to_hsl(), c.to_hsl(), d.to_hsl()]
+            }
+        };
+        Gradient::builder()
+            .top_left(corners[0])
+            .top_right(corners[1])
+            .bottom_left(corners[2])
+            .bottom_right(corners[3])
+            .build()
+    }
+
+    /// The locks of the current pattern as a mask that can be painted on
+    fn current_mask(&self) -> LockMask {
+        match &self.locking_pattern {
+            LockingPattern::Custom(mask) => mask.clone(),
+            pattern => pattern.mask(self.width, self.height),
+        }
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        if let LockingPattern::Custom(mask) = &self.locking_pattern {
+            // keep the painted cells that are still on the board
+            let mask = LockMask::from_closure(width, height, |x, y| mask.get(x, y));
+            self.locking_pattern = LockingPattern::Custom(mask);
+        }
+        self.width = width;
+        self.height = height;
+    }
+
+    fn corner_input(&self, corner: usize) -> Html {
+        html! {
+            <label>
+                {CORNER_NAMES[corner]}
+                <input type="color" value=self.corners[corner].to_hex()
+                    oninput=self.link.callback(move |data: InputData| Msg::SetCorner(corner, data.value)) />
+            </label>
+        }
+    }
+
+    fn locking_pattern_select(&self) -> Html {
+        let is_painted = matches!(self.locking_pattern, LockingPattern::Custom(_));
+        html! {
+            <label>
+                {"Locks"}
+                <select onchange=self.link.callback(Msg::SetLockingPattern)>
+                    <option value=PAINTED selected=is_painted>{"Painted"}</option>
+                    {
+                        for LockingPattern::PRESETS.iter().enumerate().map(|(index, pattern)| html! {
+                            <option value=index selected=*pattern == self.locking_pattern>
+                                {pattern.name()}
+                            </option>
+                        })
+                    }
+                </select>
+            </label>
+        }
+    }
+
+    fn moves_select(&self) -> Html {
+        html! {
+            <label>
+                {"Moves"}
+                <select onchange=self.link.callback(Msg::SetMoves)>
+                    {
+                        for MoveKind::ALL.iter().enumerate().map(|(index, &moves)| html! {
+                            <option value=index selected=moves == self.moves>
+                                {moves.name()}
+                            </option>
+                        })
+                    }
+                </select>
+            </label>
+        }
+    }
+
+    fn actions(&self) -> Html {
+        let error = self.settings().err();
+        let play_class = if error.is_none() {
+            "hud-button"
+        } else {
+            "hud-button disabled"
+        };
+        html! {
+            <>
+                {
+                    if let Some(error) = error {
+                        html! { <div class="editor-error">{error.to_string()}</div> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if let Some(code) = &self.share_code {
+                        html! { <input class="editor-code" readonly=true value=code /> }
+                    } else {
+                        html! {}
+                    }
+                }
+                <div class="editor-actions">
+                    <div class=play_class onclick=self.link.callback(|_| Msg::Play)>{"Play"}</div>
+                    <div class=play_class onclick=self.link.callback(|_| Msg::Export)>{"Export"}</div>
+                    <div class=play_class onclick=self.link.callback(|_| Msg::CopyLink)>{"Copy Link"}</div>
+                    <div class="hud-button" onclick=self.link.callback(|_| Msg::Close)>{"Back"}</div>
+                </div>
+            </>
+        }
+    }
+
+    /// Shows the board even when the settings can't be played, so the
+    /// problem can be seen and fixed
+    fn preview_grid(&self) -> Html {
+        let (width, height) = (self.width, self.height);
+        let gradient = self.gradient();
+        let mask = self.current_mask();
+        html! {
+            <div
+                class="editor-preview"
+                style=format!("--grid-width: {}; --grid-height: {}", width, height)>
+                {
+                    for (0..width * height).map(|index| {
+                        let (x, y) = (index % width, index / width);
+                        let color = gradient.color_at(puzzle::cell_position(x, y, width, height));
+                        let style = format!("background: {}", color.to_css());
+                        html! {
+                            <div class="cell interactive" onclick=self.link.callback(move |_| Msg::PaintCell(index))>
+                                <div class="tile" style=style>
+                                    { if mask.get(x, y) { html! {<div class="lock" />} } else { html! {} } }
+                                </div>
+                            </div>
+                        }
+                    })
+                }
+            </div>
+        }
+    }
+}
+
+fn parse_size(value: &str) -> Option<usize> {
+    let size = value.parse::<usize>().ok()?;
+    if (MIN_SIZE..=MAX_SIZE).contains(&size) {
+        Some(size)
+    } else {
+        None
+    }
+}
@@ -110,6 +110,36 @@ impl Color {
             Self::HSL { .. } => self,
         }
     }
+
+    /// Parses a css style `#rrggbb` color
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let hex = hex.strip_prefix('#')?;
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+        Some(Self::rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    pub fn to_hex(self) -> String {
+        let (r, g, b) = self.to_rgb().unwrap_rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    pub fn to_lab(self) -> (f64, f64, f64) {
+        color_conversions::rgb_to_lab(self.to_rgb().unwrap_rgb())
+    }
+
+    /// Perceptual distance between two colors (CIE76 delta E).
+    /// A distance of around 2.3 is the smallest difference most people notice.
+    pub fn distance(self, other: Self) -> f64 {
+        lab_distance(self.to_lab(), other.to_lab())
+    }
+}
+
+/// `Color::distance` of two colors that were already converted with `to_lab`
+pub fn lab_distance(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -151,6 +181,16 @@ impl Gradient {
         GradientBuilder::new()
     }
 
+    /// The corner colors as (top-left, top-right, bottom-left, bottom-right)
+    pub fn corners(&self) -> [Color; 4] {
+        [
+            self.top_left.color,
+            self.top_right.color,
+            self.bottom_left.color,
+            self.bottom_right.color,
+        ]
+    }
+
     pub fn color_at(&self, at: Position) -> Color {
         let top_color = Color::lerp(self.top_left.color, self.top_right.color, at.x);
         let bottom_color = Color::lerp(self.bottom_left.color, self.bottom_right.color, at.x);
@@ -242,6 +282,24 @@ mod tests {
         assert!(u8_diff(a.2, b.2) <= 1);
     }
 
+    #[test]
+    fn hex() {
+        assert_eq!(Color::from_hex("#ff8000"), Some(Color::rgb(255, 128, 0)));
+        assert_eq!(Color::rgb(1, 171, 255).to_hex(), "#01abff");
+        assert_eq!(Color::from_hex("ff8000"), None);
+        assert_eq!(Color::from_hex("#ff800"), None);
+        assert_eq!(Color::from_hex("#ff80zz"), None);
+    }
+
+    #[test]
+    fn distance() {
+        let red = Color::rgb(255, 0, 0);
+        assert_eq!(red.distance(red), 0.0);
+        assert_eq!(red.distance(red.to_hsl()), 0.0);
+        assert!((Color::rgb(0, 0, 0).distance(Color::rgb(255, 255, 255)) - 100.0).abs() < 0.01);
+        assert!(Color::rgb(100, 100, 100).distance(Color::rgb(101, 100, 100)) < 1.0);
+    }
+
     #[test]
     fn rgb_to_hsl() {
         for r in 0..255 {
@@ -90,3 +90,39 @@ pub fn rgb_to_hsl((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
 
     (h, s, l)
 }
+
+// source: http://www.easyrgb.com/en/math.php (sRGB -> XYZ -> CIE-L*ab, D65 illuminant)
+#[allow(clippy::many_single_char_names)]
+pub fn rgb_to_lab((r, g, b): (u8, u8, u8)) -> (f64, f64, f64) {
+    fn linearize(v: u8) -> f64 {
+        let v = f64::from(v) / 255.0;
+        if v > 0.040_45 {
+            ((v + 0.055) / 1.055).powf(2.4)
+        } else {
+            v / 12.92
+        }
+    }
+
+    fn pivot(v: f64) -> f64 {
+        if v > 0.008_856 {
+            v.cbrt()
+        } else {
+            7.787 * v + 16.0 / 116.0
+        }
+    }
+
+    let r = linearize(r);
+    let g = linearize(g);
+    let b = linearize(b);
+
+    // normalized to the D65 reference white
+    let x = (r * 0.4124 + g * 0.3576 + b * 0.1805) / 0.950_47;
+    let y = r * 0.2126 + g * 0.7152 + b * 0.0722;
+    let z = (r * 0.0193 + g * 0.1192 + b * 0.9505) / 1.088_83;
+
+    let x = pivot(x);
+    let y = pivot(y);
+    let z = pivot(z);
+
+    (116.0 * y - 16.0, 500.0 * (x - y), 200.0 * (y - z))
+}
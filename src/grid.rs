@@ -41,6 +41,10 @@ impl<T> Grid<T> {
     pub fn get(&self, idx: usize) -> &T {
         &self.cells[idx]
     }
+
+    pub fn get_mut(&mut self, idx: usize) -> &mut T {
+        &mut self.cells[idx]
+    }
 }
 
 pub struct Iter<'a, T> {
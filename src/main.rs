@@ -1,35 +1,156 @@
+#![recursion_limit = "256"]
+
+use std::time::Duration;
 use stdweb::js;
 use web_logger;
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
 use yew::{html, Callback, ClickEvent, Component, ComponentLink, Html, ShouldRender};
 
+mod challenge;
+mod clock;
+mod daily;
 mod debug;
+mod editor;
 mod gradient;
 mod grid;
+mod memory;
 mod puzzle;
 mod puzzle_view;
 mod savegame;
+mod share_link;
+mod stats;
+mod time_attack;
+mod zen;
 
-use puzzle::Puzzle;
+use daily::{DailyRecord, DailyResult};
+use editor::EditorView;
+use puzzle::{DifficultyProfile, Puzzle, PEEKS_PER_LEVEL};
 use puzzle_view::PuzzleView;
+use stats::LevelRecord;
+use time_attack::{TimeAttackRecord, TimeAttackRun};
+use zen::ZenSettings;
 
 pub const SAVEGAME_KEY: &str = "SAVEGAME";
+pub const SHARED_SAVEGAME_KEY: &str = "SHARED_SAVEGAME";
+pub const DAILY_SAVEGAME_KEY: &str = "DAILY_SAVEGAME";
+pub const CUSTOM_SAVEGAME_KEY: &str = "CUSTOM_SAVEGAME";
+pub const CHALLENGE_SAVEGAME_KEY: &str = "CHALLENGE_SAVEGAME";
+pub const MEMORY_SAVEGAME_KEY: &str = "MEMORY_SAVEGAME";
+pub const ZEN_SAVEGAME_KEY: &str = "ZEN_SAVEGAME";
+pub const TIME_ATTACK_SAVEGAME_KEY: &str = "TIME_ATTACK_SAVEGAME";
+pub const TIME_ATTACK_KEY: &str = "TIME_ATTACK";
+pub const TIME_ATTACK_STATS_KEY: &str = "TIME_ATTACK_STATS";
+pub const STATS_KEY: &str = "STATS";
+pub const DAILY_STATS_KEY: &str = "DAILY_STATS";
+pub const PROFILE_KEY: &str = "PROFILE";
+pub const ZEN_KEY: &str = "ZEN";
+pub const HIDDEN_LOCKS_KEY: &str = "HIDDEN_LOCKS";
+pub const AUTO_LOCK_KEY: &str = "AUTO_LOCK";
+
+/// How often the time attack countdown is updated
+const TICK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a solved zen board takes to fade into the next one
+const FADE_DURATION: Duration = Duration::from_secs(1);
 
 enum GameState {
     Initial,
+    Editing,
+    /// The solved memory board is shown before it's shuffled
+    Studying,
+    /// The tiles of the memory board fly to their shuffled positions
+    Shuffling,
     Playing,
+    /// The time attack countdown is stopped and the board hidden
+    Paused,
+    /// The time attack countdown ran out
+    Expired,
+    /// The challenge board ran out of moves
+    Failed,
     GameOver,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum GameMode {
+    /// The main progression of ever harder levels
+    Levels,
+    /// A puzzle opened from a share link
+    Shared,
+    /// The puzzle of the given day (since the unix epoch)
+    Daily(u64),
+    /// A puzzle made in the editor
+    Custom,
+    /// Boards played against a countdown until it runs out
+    TimeAttack,
+    /// Ever harder boards that only allow a few moves more than par
+    Challenge,
+    /// Boards of a chosen difficulty one after another, without any scoring
+    Zen,
+    /// Ever harder boards that are shown solved before they're shuffled, and
+    /// have their locks hidden
+    Memory,
+}
+
+impl GameMode {
+    fn save_key(self) -> &'static str {
+        match self {
+            GameMode::Levels => SAVEGAME_KEY,
+            GameMode::Shared => SHARED_SAVEGAME_KEY,
+            GameMode::Daily(_) => DAILY_SAVEGAME_KEY,
+            GameMode::Custom => CUSTOM_SAVEGAME_KEY,
+            GameMode::TimeAttack => TIME_ATTACK_SAVEGAME_KEY,
+            GameMode::Challenge => CHALLENGE_SAVEGAME_KEY,
+            GameMode::Zen => ZEN_SAVEGAME_KEY,
+            GameMode::Memory => MEMORY_SAVEGAME_KEY,
+        }
+    }
+}
+
 struct App {
     link: ComponentLink<Self>,
     state: GameState,
+    mode: GameMode,
+    profile: &'static DifficultyProfile,
+    /// Whether new boards hide their locks
+    hidden_locks: bool,
+    /// Whether tiles lock themselves once they're put in place
+    auto_lock: bool,
     puzzle: Puzzle,
+    /// Counts the retries of the board, which restart it with the same shuffle
+    attempt: usize,
+    time_attack: Option<TimeAttackRun>,
+    zen: ZenSettings,
+    /// The solved zen board while it fades out
+    fading: Option<(Puzzle, TimeoutTask)>,
+    /// Ends the current phase of a memory board
+    phase_timer: Option<TimeoutTask>,
+    /// Runs while the time attack countdown does, along with when it last ticked
+    timer: Option<(IntervalTask, f64)>,
 }
 
 enum Msg {
-    StartGame,
-    CompletePuzzle,
+    StartGame(GameMode),
+    SetProfile(&'static DifficultyProfile),
+    ToggleHiddenLocks,
+    ToggleAutoLock,
+    CompletePuzzle(Box<Puzzle>),
+    FailPuzzle(Box<Puzzle>),
+    RetryPuzzle,
     NextLevel,
+    CopyDailyResult,
+    OpenEditor,
+    CloseEditor,
+    PlayCustom(Box<Puzzle>),
+    PlaceTiles(usize),
+    SetZenDifficulty(usize),
+    ToggleKeepPalette,
+    ClearFade,
+    FinishStudy,
+    FinishShuffle,
+    Tick,
+    Pause,
+    Resume,
 }
 
 impl Component for App {
@@ -37,50 +158,268 @@ impl Component for App {
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let win_size = get_win_size();
-        let puzzle = if let Some(mut puzzle) = savegame::load::<Puzzle>(SAVEGAME_KEY) {
-            // if the loaded puzzle is already solved
-            if puzzle.is_solved() {
-                // go to the next level
-                puzzle.next_level(win_size);
-                savegame::save(SAVEGAME_KEY, &puzzle);
-            }
-            puzzle
+        let profile = savegame::load::<String>(PROFILE_KEY)
+            .map_or_else(DifficultyProfile::standard, |name| {
+                DifficultyProfile::get(&name)
+            });
+        // the countdown stops whenever the page is hidden
+        let pause = link.callback(|_: ()| Msg::Pause);
+        let pause = move || pause.emit(());
+        js! { @(no_return)
+            var pause = @{pause};
+            document.addEventListener("visibilitychange", function() {
+                if (document.hidden) {
+                    pause();
+                }
+            });
+        }
+
+        let (puzzle, mode) = if let Some(puzzle) = load_shared_puzzle() {
+            (puzzle, GameMode::Shared)
         } else {
-            let puzzle = Puzzle::generate_lvl1(win_size);
-            savegame::save(SAVEGAME_KEY, &puzzle);
-            puzzle
+            let puzzle = load_progression_puzzle(SAVEGAME_KEY, profile, get_win_size());
+            (puzzle, GameMode::Levels)
         };
         App {
             link,
             state: GameState::Initial,
+            mode,
+            profile,
+            hidden_locks: savegame::load(HIDDEN_LOCKS_KEY).unwrap_or(false),
+            auto_lock: savegame::load(AUTO_LOCK_KEY).unwrap_or(false),
             puzzle,
+            attempt: 0,
+            time_attack: None,
+            zen: ZenSettings::load(ZEN_KEY),
+            fading: None,
+            phase_timer: None,
+            timer: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::StartGame => self.state = GameState::Playing,
+            Msg::StartGame(mode) => {
+                // an expired time attack run is replaced by a new one
+                if mode != self.mode || matches!(self.state, GameState::Expired) {
+                    self.switch_mode(mode);
+                }
+                self.start_board();
+            }
+            Msg::SetProfile(profile) => {
+                self.profile = profile;
+                savegame::save(PROFILE_KEY, &profile.name);
+                // the profile applies from the next level, unless the current
+                // one hasn't been started yet
+                if self.mode == GameMode::Levels && self.puzzle.stats.moves == 0 {
+                    let difficulty = self.puzzle.settings.difficulty();
+                    self.puzzle =
+                        Puzzle::generate(profile, difficulty, rand::random(), get_win_size());
+                    self.puzzle.save(SAVEGAME_KEY);
+                    self.apply_options();
+                }
+            }
+            Msg::ToggleHiddenLocks => {
+                self.hidden_locks = !self.hidden_locks;
+                savegame::save(HIDDEN_LOCKS_KEY, self.hidden_locks);
+                // like the profile, this applies from the next board unless
+                // the current one hasn't been started yet
+                self.apply_options();
+            }
+            Msg::ToggleAutoLock => {
+                self.auto_lock = !self.auto_lock;
+                savegame::save(AUTO_LOCK_KEY, self.auto_lock);
+                self.apply_options();
+            }
             Msg::NextLevel => {
-                let win_size = get_win_size();
-                self.puzzle.next_level(win_size);
-                savegame::save(SAVEGAME_KEY, &self.puzzle);
-                self.state = GameState::Playing
+                if let GameMode::Levels | GameMode::Challenge | GameMode::Memory = self.mode {
+                    let win_size = get_win_size();
+                    self.puzzle.next_level(self.profile, win_size);
+                    self.puzzle.save(self.mode.save_key());
+                } else {
+                    // everything else continues the main progression
+                    self.switch_mode(GameMode::Levels);
+                }
+                self.start_board();
+            }
+            Msg::CompletePuzzle(puzzle) => {
+                // zen boards fade straight into the next one
+                if let GameMode::Zen = self.mode {
+                    let next = self.zen.next_puzzle(&puzzle, self.profile, get_win_size());
+                    next.save(ZEN_SAVEGAME_KEY);
+                    let clear = self.link.callback(|_| Msg::ClearFade);
+                    let task = TimeoutService::new().spawn(FADE_DURATION, clear);
+                    self.fading = Some((*puzzle, task));
+                    self.puzzle = next;
+                    self.apply_options();
+                    return true;
+                }
+                // time attack runs go straight on to the next board
+                if let (GameMode::TimeAttack, Some(run)) = (self.mode, &mut self.time_attack) {
+                    if !run.is_expired() {
+                        let difficulty = run.difficulty() + 1;
+                        let next = Puzzle::generate(
+                            self.profile,
+                            difficulty,
+                            rand::random(),
+                            get_win_size(),
+                        );
+                        run.next_board(&next);
+                        run.save(TIME_ATTACK_KEY);
+                        next.save(TIME_ATTACK_SAVEGAME_KEY);
+                        self.puzzle = next;
+                        self.apply_options();
+                    }
+                    return true;
+                }
+
+                match self.mode {
+                    GameMode::Levels => {
+                        let record = LevelRecord {
+                            difficulty: puzzle.settings.difficulty(),
+                            par: puzzle.par(),
+                            stars: puzzle.stars(),
+                            stats: puzzle.stats.clone(),
+                        };
+                        stats::add_record(STATS_KEY, record);
+                    }
+                    GameMode::Daily(day) => {
+                        let mut record = DailyRecord::load(DAILY_STATS_KEY);
+                        record.add_result(DailyResult::from_puzzle(day, &puzzle));
+                        record.save(DAILY_STATS_KEY);
+                    }
+                    GameMode::Shared
+                    | GameMode::Custom
+                    | GameMode::TimeAttack
+                    | GameMode::Challenge
+                    | GameMode::Zen
+                    | GameMode::Memory => {}
+                }
+                self.puzzle = *puzzle;
+                self.state = GameState::GameOver
+            }
+            Msg::FailPuzzle(puzzle) => {
+                self.puzzle = *puzzle;
+                self.state = GameState::Failed;
+            }
+            Msg::RetryPuzzle => {
+                // the moves are taken back, so the same board comes back
+                self.puzzle = self.puzzle.restarted();
+                self.attempt += 1;
+                self.puzzle.save(self.mode.save_key());
+                self.apply_options();
+                self.state = GameState::Playing;
+            }
+            Msg::CopyDailyResult => {
+                if let GameMode::Daily(day) = self.mode {
+                    let record = DailyRecord::load(DAILY_STATS_KEY);
+                    if let Some(result) = record.result(day) {
+                        share_link::copy_text(&result.summary(record.current_streak(day)));
+                    }
+                }
+                return false;
+            }
+            Msg::OpenEditor => self.state = GameState::Editing,
+            Msg::CloseEditor => self.state = GameState::Initial,
+            Msg::PlayCustom(puzzle) => {
+                if let GameMode::Shared = self.mode {
+                    share_link::clear_url();
+                }
+                puzzle.save(CUSTOM_SAVEGAME_KEY);
+                self.puzzle = *puzzle;
+                self.mode = GameMode::Custom;
+                self.state = GameState::Playing;
+                self.time_attack = None;
+                self.apply_options();
+            }
+            Msg::PlaceTiles(placed) => match &mut self.time_attack {
+                Some(run) => {
+                    run.place_tiles(placed);
+                    run.save(TIME_ATTACK_KEY);
+                }
+                None => return false,
+            },
+            Msg::SetZenDifficulty(difficulty) => {
+                self.zen.set_difficulty(difficulty);
+                self.zen.save(ZEN_KEY);
+                self.puzzle = Puzzle::generate(
+                    self.profile,
+                    self.zen.difficulty,
+                    rand::random(),
+                    get_win_size(),
+                );
+                self.puzzle.save(ZEN_SAVEGAME_KEY);
+                self.apply_options();
+            }
+            Msg::ToggleKeepPalette => {
+                self.zen.keep_palette = !self.zen.keep_palette;
+                self.zen.save(ZEN_KEY);
+            }
+            Msg::ClearFade => self.fading = None,
+            Msg::FinishStudy => {
+                let finish = self.link.callback(|_| Msg::FinishShuffle);
+                let task = TimeoutService::new().spawn(memory::SHUFFLE_DURATION, finish);
+                self.phase_timer = Some(task);
+                self.state = GameState::Shuffling;
+            }
+            Msg::FinishShuffle => {
+                self.phase_timer = None;
+                self.state = GameState::Playing;
             }
-            Msg::CompletePuzzle => self.state = GameState::GameOver,
+            Msg::Tick => self.tick_time_attack(),
+            Msg::Pause => {
+                if self.timer.is_none() {
+                    return false;
+                }
+                self.tick_time_attack();
+                if let GameState::Playing = self.state {
+                    self.state = GameState::Paused;
+                }
+            }
+            Msg::Resume => self.state = GameState::Playing,
         }
+        self.sync_timer();
         true
     }
 
     fn view(&self) -> Html {
+        if let GameState::Editing = self.state {
+            return html! {
+                <EditorView
+                    onplay=self.link.callback(|puzzle| Msg::PlayCustom(Box::new(puzzle)))
+                    onclose=self.link.callback(|_| Msg::CloseEditor) />
+            };
+        }
+
         html! {
             <>
-                <PuzzleView puzzle=self.puzzle.clone() oncomplete=self.link.callback(|_| Msg::CompletePuzzle) />
+                <PuzzleView
+                    puzzle=self.puzzle.clone()
+                    save_key=self.mode.save_key()
+                    move_limit=self.move_limit()
+                    peek_limit=self.peek_limit()
+                    unscored=self.mode == GameMode::Zen
+                    active=matches!(self.state, GameState::Playing)
+                    memory_hidden=self.mode == GameMode::Memory
+                        && !matches!(self.state, GameState::Studying | GameState::Shuffling)
+                    attempt=self.attempt
+                    onmove=self.link.callback(Msg::PlaceTiles)
+                    oncomplete=self.link.callback(|puzzle| Msg::CompletePuzzle(Box::new(puzzle)))
+                    onfail=self.link.callback(|puzzle| Msg::FailPuzzle(Box::new(puzzle))) />
+                { self.board_cover() }
+                { self.time_attack_hud() }
+                { self.zen_hud() }
                 {
                     match self.state {
-                        GameState::Initial => start_game_ui_overlay(self.link.callback(|_| Msg::StartGame)),
-                        GameState::Playing => html!{},
-                        GameState::GameOver => game_over_ui_overlay(self.link.callback(|_| Msg::NextLevel)),
+                        GameState::Initial => self.start_game_ui_overlay(),
+                        GameState::Editing
+                        | GameState::Studying
+                        | GameState::Shuffling
+                        | GameState::Playing => html!{},
+                        GameState::Paused => self.paused_ui_overlay(),
+                        GameState::Expired => self.expired_ui_overlay(),
+                        GameState::Failed => self.failed_ui_overlay(),
+                        GameState::GameOver => self.game_over_ui_overlay(),
                     }
                 }
             </>
@@ -88,6 +427,431 @@ impl Component for App {
     }
 }
 
+impl App {
+    /// The board shown on top of the one being played while it can't be
+    /// played yet, or while a solved one fades away
+    fn board_cover(&self) -> Html {
+        match (&self.state, &self.fading) {
+            // the anchors are what a memory board is studied for
+            (GameState::Studying, _) => {
+                let solved = Puzzle::unshuffled(self.puzzle.settings.clone());
+                puzzle_view::static_grid(&solved, "studying", true)
+            }
+            (GameState::Shuffling, _) => {
+                let show_locks = !self.puzzle.settings.hidden_locks();
+                puzzle_view::static_grid(&self.puzzle, "shuffling", show_locks)
+            }
+            (_, Some((puzzle, _))) => {
+                let show_locks = !puzzle.settings.hidden_locks() && self.mode != GameMode::Memory;
+                puzzle_view::static_grid(puzzle, "fading", show_locks)
+            }
+            _ => html! {},
+        }
+    }
+
+    fn switch_mode(&mut self, mode: GameMode) {
+        if let GameMode::Shared = self.mode {
+            share_link::clear_url();
+        }
+        self.puzzle = match mode {
+            GameMode::Levels | GameMode::Challenge | GameMode::Memory => {
+                load_progression_puzzle(mode.save_key(), self.profile, get_win_size())
+            }
+            GameMode::Daily(day) => load_daily_puzzle(day),
+            GameMode::Shared => {
+                load_shared_puzzle().expect("Shared puzzles are only loaded on startup")
+            }
+            GameMode::Custom => match load_puzzle(CUSTOM_SAVEGAME_KEY) {
+                Some(puzzle) => puzzle,
+                None => return self.switch_mode(GameMode::Levels),
+            },
+            GameMode::Zen => load_zen_puzzle(&self.zen, self.profile, get_win_size()),
+            GameMode::TimeAttack => {
+                let (run, puzzle) = load_time_attack(self.profile, get_win_size());
+                self.time_attack = Some(run);
+                puzzle
+            }
+        };
+        if mode != GameMode::TimeAttack {
+            self.time_attack = None;
+        }
+        self.fading = None;
+        self.phase_timer = None;
+        self.mode = mode;
+    }
+
+    /// Applies the player's options to the board. The auto-lock assist can be
+    /// switched at any time, the hidden locks option only if the board hasn't
+    /// been started. Boards from codes keep their own hidden locks setting.
+    fn apply_options(&mut self) {
+        if self.puzzle.auto_lock() != self.auto_lock {
+            self.puzzle.set_auto_lock(self.auto_lock);
+            self.puzzle.save(self.mode.save_key());
+        }
+
+        let hidden = match self.mode {
+            GameMode::Levels
+            | GameMode::Challenge
+            | GameMode::TimeAttack
+            | GameMode::Zen
+            | GameMode::Memory => self.hidden_locks,
+            GameMode::Daily(_) | GameMode::Shared | GameMode::Custom => return,
+        };
+        if self.puzzle.stats.moves == 0 && self.puzzle.settings.hidden_locks() != hidden {
+            self.puzzle.set_hidden_locks(hidden);
+            self.puzzle.save(self.mode.save_key());
+        }
+    }
+
+    /// Picks the state a newly started or resumed board begins in
+    fn start_board(&mut self) {
+        self.apply_options();
+        // today's daily puzzle may have already been solved, and a challenge
+        // board may have already been failed
+        self.state = if self.puzzle.is_solved() {
+            GameState::GameOver
+        } else if self.mode == GameMode::Challenge && challenge::is_failed(&self.puzzle) {
+            GameState::Failed
+        } else if self.mode == GameMode::Memory && self.puzzle.stats.moves == 0 {
+            let study_time = memory::study_time(self.puzzle.settings.difficulty());
+            let finish = self.link.callback(|_| Msg::FinishStudy);
+            self.phase_timer = Some(TimeoutService::new().spawn(study_time, finish));
+            GameState::Studying
+        } else {
+            GameState::Playing
+        };
+    }
+
+    /// Challenge boards allow a few moves more than par, everything else is
+    /// unlimited
+    fn move_limit(&self) -> Option<usize> {
+        if let GameMode::Challenge = self.mode {
+            Some(challenge::move_limit(&self.puzzle))
+        } else {
+            None
+        }
+    }
+
+    /// Peeking is limited in the modes that keep score, and free on boards
+    /// that are just for fun
+    fn peek_limit(&self) -> Option<usize> {
+        match self.mode {
+            GameMode::Levels
+            | GameMode::Daily(_)
+            | GameMode::TimeAttack
+            | GameMode::Challenge
+            | GameMode::Memory => Some(PEEKS_PER_LEVEL),
+            GameMode::Shared | GameMode::Custom | GameMode::Zen => None,
+        }
+    }
+
+    /// Takes the time since the last tick off the countdown, ending the run
+    /// once it runs out
+    fn tick_time_attack(&mut self) {
+        let (run, (_, last_tick)) = match (&mut self.time_attack, &mut self.timer) {
+            (Some(run), Some(timer)) => (run, timer),
+            _ => return,
+        };
+        let now = clock::now_ms();
+        run.tick(now - *last_tick);
+        *last_tick = now;
+        run.save(TIME_ATTACK_KEY);
+
+        if run.is_expired() {
+            let mut record = TimeAttackRecord::load(TIME_ATTACK_STATS_KEY);
+            record.add_run(run);
+            record.save(TIME_ATTACK_STATS_KEY);
+            self.state = GameState::Expired;
+        }
+    }
+
+    /// Starts or stops the countdown to match the state of the game
+    fn sync_timer(&mut self) {
+        let should_run = self.time_attack.is_some() && matches!(self.state, GameState::Playing);
+        if !should_run {
+            self.timer = None;
+        } else if self.timer.is_none() {
+            let task =
+                IntervalService::new().spawn(TICK_INTERVAL, self.link.callback(|_| Msg::Tick));
+            self.timer = Some((task, clock::now_ms()));
+        }
+    }
+
+    fn time_attack_hud(&self) -> Html {
+        let run = match (&self.time_attack, &self.state) {
+            (Some(run), GameState::Playing) => run,
+            _ => return html! {},
+        };
+        html! {
+            <div class="time-attack-hud">
+                <div class="hud-label">
+                    {format!("{} / Level {}", clock::format_duration(run.remaining_ms), run.difficulty())}
+                </div>
+                <div class="hud-button" onclick=self.link.callback(|_| Msg::Pause)>{"Pause"}</div>
+            </div>
+        }
+    }
+
+    fn zen_hud(&self) -> Html {
+        if self.mode != GameMode::Zen || !matches!(self.state, GameState::Playing) {
+            return html! {};
+        }
+        let difficulty = self.zen.difficulty;
+        let palette_class = if self.zen.keep_palette {
+            "hud-button selected"
+        } else {
+            "hud-button"
+        };
+        html! {
+            <div class="zen-hud">
+                <div class="hud-button" onclick=self.link.callback(move |_| Msg::SetZenDifficulty(difficulty - 1))>
+                    {"-"}
+                </div>
+                <div class="hud-label">{format!("Difficulty {}", difficulty)}</div>
+                <div class="hud-button" onclick=self.link.callback(move |_| Msg::SetZenDifficulty(difficulty + 1))>
+                    {"+"}
+                </div>
+                <div class=palette_class onclick=self.link.callback(|_| Msg::ToggleKeepPalette)>
+                    {"Keep Palette"}
+                </div>
+            </div>
+        }
+    }
+
+    fn paused_ui_overlay(&self) -> Html {
+        html! {
+            <div class="ui-overlay paused">
+                <div class="ui-menu">
+                    <div class="ui-text" onclick=self.link.callback(|_| Msg::Resume)>{"Resume"}</div>
+                </div>
+            </div>
+        }
+    }
+
+    fn failed_ui_overlay(&self) -> Html {
+        html! {
+            <div class="ui-overlay">
+                <div class="ui-menu">
+                    <div class="ui-subtext">{"Out of moves"}</div>
+                    <div class="ui-subtext">
+                        {format!("{} allowed (par {})", challenge::move_limit(&self.puzzle), self.puzzle.par())}
+                    </div>
+                    <div class="ui-text" onclick=self.link.callback(|_| Msg::RetryPuzzle)>{"Retry"}</div>
+                </div>
+            </div>
+        }
+    }
+
+    fn expired_ui_overlay(&self) -> Html {
+        let levels_cleared = self
+            .time_attack
+            .as_ref()
+            .map_or(0, |run| run.levels_cleared);
+        let record = TimeAttackRecord::load(TIME_ATTACK_STATS_KEY);
+        let retry = self.link.callback(|_| Msg::StartGame(GameMode::TimeAttack));
+        html! {
+            <div class="ui-overlay">
+                <div class="ui-menu">
+                    <div class="ui-subtext">{"Time's up!"}</div>
+                    <div class="ui-subtext">
+                        {format!("{} levels cleared (best {})", levels_cleared, record.best_levels)}
+                    </div>
+                    <div class="ui-text" onclick=retry>{"Try Again"}</div>
+                    <div class="ui-subtext ui-option" onclick=self.link.callback(|_| Msg::NextLevel)>
+                        {"Back to Levels"}
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    fn start_game_ui_overlay(&self) -> Html {
+        let mode = self.mode;
+        let start = self.link.callback(move |_| Msg::StartGame(mode));
+        let mode_option = |label: &str, mode: GameMode| {
+            html! {
+                <div class="ui-subtext ui-option" onclick=self.link.callback(move |_| Msg::StartGame(mode))>
+                    {label}
+                </div>
+            }
+        };
+        html! {
+            <div class="ui-overlay">
+                <div class="ui-menu">
+                    <div class="ui-text" onclick=start>{"Start"}</div>
+                    { self.profile_options() }
+                    { mode_option("Daily Puzzle", GameMode::Daily(daily::today())) }
+                    { mode_option("Time Attack", GameMode::TimeAttack) }
+                    { mode_option("Challenge", GameMode::Challenge) }
+                    { mode_option("Zen", GameMode::Zen) }
+                    { mode_option("Memory", GameMode::Memory) }
+                    <div class="ui-subtext ui-option" onclick=self.link.callback(|_| Msg::OpenEditor)>
+                        {"Create Puzzle"}
+                    </div>
+                </div>
+            </div>
+        }
+    }
+
+    /// The difficulty profiles and the hidden locks and auto-lock options,
+    /// which apply to every generated board
+    fn profile_options(&self) -> Html {
+        let option_class = |selected| {
+            if selected {
+                "ui-subtext ui-option selected"
+            } else {
+                "ui-subtext ui-option"
+            }
+        };
+        html! {
+            <div class="ui-profiles">
+                {
+                    for DifficultyProfile::all().iter().map(|profile| {
+                        let class = option_class(std::ptr::eq(profile, self.profile));
+                        html! {
+                            <div class=class onclick=self.link.callback(move |_| Msg::SetProfile(profile))>
+                                {&profile.name}
+                            </div>
+                        }
+                    })
+                }
+                <div class=option_class(self.hidden_locks) onclick=self.link.callback(|_| Msg::ToggleHiddenLocks)>
+                    {"Hidden Locks"}
+                </div>
+                <div class=option_class(self.auto_lock) onclick=self.link.callback(|_| Msg::ToggleAutoLock)>
+                    {"Auto-Lock"}
+                </div>
+            </div>
+        }
+    }
+
+    fn game_over_ui_overlay(&self) -> Html {
+        let onclick = self.link.callback(|_| Msg::NextLevel);
+        match self.mode {
+            GameMode::Daily(day) => {
+                let record = DailyRecord::load(DAILY_STATS_KEY);
+                let summary = record
+                    .result(day)
+                    .map(|result| result.summary(record.current_streak(day)))
+                    .unwrap_or_default();
+                let copy_result = self.link.callback(|_| Msg::CopyDailyResult);
+                html! {
+                    <div class="ui-overlay">
+                        <div class="ui-menu">
+                            <div class="ui-stars">{stats::format_stars(self.puzzle.stars())}</div>
+                            <pre class="ui-subtext">{summary}</pre>
+                            <div class="ui-subtext ui-option" onclick=copy_result>{"Copy Result"}</div>
+                            <div class="ui-text" onclick=onclick>{"Continue"}</div>
+                        </div>
+                    </div>
+                }
+            }
+            GameMode::Levels
+            | GameMode::Shared
+            | GameMode::Custom
+            | GameMode::TimeAttack
+            | GameMode::Challenge
+            | GameMode::Zen
+            | GameMode::Memory => game_over_ui_overlay(&self.puzzle, onclick),
+        }
+    }
+}
+
+/// Loads a saved puzzle, ignoring saves that can't be played
+fn load_puzzle(key: &str) -> Option<Puzzle> {
+    let puzzle = Puzzle::load(key)?;
+    match puzzle.validate() {
+        Ok(()) => Some(puzzle),
+        Err(err) => {
+            log::warn!("Ignoring invalid saved puzzle {:?}: {}", key, err);
+            None
+        }
+    }
+}
+
+/// Resumes the time attack run in progress, or starts a new one if the last
+/// one ran out
+fn load_time_attack(
+    profile: &DifficultyProfile,
+    win_size: (usize, usize),
+) -> (TimeAttackRun, Puzzle) {
+    let saved = TimeAttackRun::load(TIME_ATTACK_KEY)
+        .filter(|run| !run.is_expired())
+        .and_then(|run| Some((run, load_puzzle(TIME_ATTACK_SAVEGAME_KEY)?)));
+    if let Some(saved) = saved {
+        return saved;
+    }
+
+    let puzzle = Puzzle::generate(profile, 1, rand::random(), win_size);
+    let run = TimeAttackRun::new(&puzzle);
+    run.save(TIME_ATTACK_KEY);
+    puzzle.save(TIME_ATTACK_SAVEGAME_KEY);
+    (run, puzzle)
+}
+
+/// Resumes the zen board being played, or starts a new one
+fn load_zen_puzzle(
+    zen: &ZenSettings,
+    profile: &DifficultyProfile,
+    win_size: (usize, usize),
+) -> Puzzle {
+    let puzzle = load_puzzle(ZEN_SAVEGAME_KEY)
+        .filter(|saved| !saved.is_solved())
+        .unwrap_or_else(|| Puzzle::generate(profile, zen.difficulty, rand::random(), win_size));
+    puzzle.save(ZEN_SAVEGAME_KEY);
+    puzzle
+}
+
+/// Loads the current level of a progression of ever harder levels
+fn load_progression_puzzle(
+    key: &str,
+    profile: &DifficultyProfile,
+    win_size: (usize, usize),
+) -> Puzzle {
+    if let Some(mut puzzle) = load_puzzle(key) {
+        // if the loaded puzzle is already solved
+        if puzzle.is_solved() {
+            // go to the next level
+            puzzle.next_level(profile, win_size);
+            puzzle.save(key);
+        }
+        puzzle
+    } else {
+        let puzzle = Puzzle::generate_lvl1(profile, win_size);
+        puzzle.save(key);
+        puzzle
+    }
+}
+
+/// Loads the daily puzzle, resuming it if it was already being played
+fn load_daily_puzzle(day: u64) -> Puzzle {
+    let puzzle = daily::generate(day);
+    let puzzle = load_puzzle(DAILY_SAVEGAME_KEY)
+        .filter(|saved| saved.settings == puzzle.settings)
+        .unwrap_or(puzzle);
+    puzzle.save(DAILY_SAVEGAME_KEY);
+    puzzle
+}
+
+/// Loads the puzzle from the share code in the url, resuming it if it was
+/// already being played
+fn load_shared_puzzle() -> Option<Puzzle> {
+    let code = share_link::code_from_url()?;
+    let puzzle = match Puzzle::from_share_code(&code) {
+        Ok(puzzle) => puzzle,
+        Err(err) => {
+            log::warn!("Ignoring invalid share code {:?}: {:?}", code, err);
+            return None;
+        }
+    };
+
+    let puzzle = load_puzzle(SHARED_SAVEGAME_KEY)
+        .filter(|saved| saved.settings == puzzle.settings && !saved.is_solved())
+        .unwrap_or(puzzle);
+    puzzle.save(SHARED_SAVEGAME_KEY);
+    Some(puzzle)
+}
+
 fn get_win_size() -> (usize, usize) {
     use stdweb::unstable::TryInto;
 
@@ -100,18 +864,17 @@ fn get_win_size() -> (usize, usize) {
     (win_width, win_height)
 }
 
-fn start_game_ui_overlay(onclick: Callback<ClickEvent>) -> Html {
-    html! {
-        <div class="ui-overlay">
-            <div class="ui-text" onclick=onclick>{"Start"}</div>
-        </div>
-    }
-}
-
-fn game_over_ui_overlay(onclick: Callback<ClickEvent>) -> Html {
+fn game_over_ui_overlay(puzzle: &Puzzle, onclick: Callback<ClickEvent>) -> Html {
     html! {
         <div class="ui-overlay">
-            <div class="ui-text" onclick=onclick>{"Play Again"}</div>
+            <div class="ui-menu">
+                <div class="ui-stars">{stats::format_stars(puzzle.stars())}</div>
+                <div class="ui-subtext">
+                    {format!("{} moves (par {})", puzzle.stats.moves, puzzle.par())}
+                    { if puzzle.stats.auto_locked { " with auto-lock" } else { "" } }
+                </div>
+                <div class="ui-text" onclick=onclick>{"Play Again"}</div>
+            </div>
         </div>
     }
 }
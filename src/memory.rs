@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+/// How long the solved board is shown for at the start of the progression,
+/// and the least it's ever shown for
+const MAX_STUDY_MS: f64 = 8000.0;
+const MIN_STUDY_MS: f64 = 2000.0;
+
+/// The difficulty at which the study time is half way between the two
+const STUDY_MIDPOINT: f64 = 20.0;
+
+/// How long the tiles take to fly from their solved positions to the shuffle
+pub const SHUFFLE_DURATION: Duration = Duration::from_millis(1000);
+
+/// How long the solved board of the given difficulty can be studied for
+pub fn study_time(difficulty: usize) -> Duration {
+    let progress = difficulty.saturating_sub(1) as f64;
+    let progress = progress / (progress + STUDY_MIDPOINT);
+    let ms = MAX_STUDY_MS - (MAX_STUDY_MS - MIN_STUDY_MS) * progress;
+    Duration::from_millis(ms.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn study_time_shrinks_with_difficulty() {
+        assert_eq!(study_time(1).as_millis(), MAX_STUDY_MS as u128);
+        assert_eq!(study_time(21).as_millis(), 5000);
+        let times = [1, 10, 100, 1000]
+            .iter()
+            .map(|&difficulty| study_time(difficulty))
+            .collect::<Vec<_>>();
+        assert!(times.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(study_time(usize::MAX).as_millis() >= MIN_STUDY_MS as u128);
+    }
+}
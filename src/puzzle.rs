@@ -1,87 +1,456 @@
 use crate::debug;
-use crate::gradient::{Color, Gradient, Position};
+use crate::gradient::{self, Color, Gradient, Position};
 use crate::grid::{Grid, Iter as GridIter};
-use lazy_static::lazy_static;
-use rand::distributions::WeightedIndex;
+use crate::savegame;
+use crate::stats::{self, LevelStats};
 use rand::prelude::*;
 use rand_distr::Normal;
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+mod adjacent_solver;
+mod difficulty;
+mod legacy;
+mod lock_mask;
+mod lock_support;
+mod locking_pattern;
+mod profile;
+mod share_code;
+mod shift_solver;
+mod shuffle;
+
+pub use difficulty::DifficultyEstimate;
+pub use lock_mask::LockMask;
+pub use lock_support::LockSupport;
+pub use locking_pattern::LockingPattern;
+pub use profile::DifficultyProfile;
+use profile::NormalRange;
+pub use share_code::ShareCodeError;
+pub use shuffle::ShuffleStrategy;
+
+pub const HINTS_PER_LEVEL: usize = 3;
+
+/// Bumped whenever the saved layout of a puzzle changes
+const SAVE_VERSION: u32 = 1;
+
+/// How many times the solved board can be peeked at in the modes that keep
+/// score
+pub const PEEKS_PER_LEVEL: usize = 3;
+
+/// Tiles whose colors are closer than this (in delta E) are treated as
+/// interchangeable by the solved check. Kept below the ~2.3 delta E that is
+/// considered just noticeable so that visually distinct tiles never match.
+pub const DEFAULT_COLOR_TOLERANCE: f64 = 1.0;
+
+/// How many random shifts a board played with shifts is scrambled with for
+/// each line that can be shifted, and the fewest it's scrambled with
+const SCRAMBLE_SHIFTS_PER_LINE: usize = 2;
+const MIN_SCRAMBLE_SHIFTS: usize = 3;
+
+/// How many random swaps of neighbours a board played with adjacent swaps is
+/// scrambled with for each unlocked tile
+const SCRAMBLE_SWAPS_PER_TILE: usize = 1;
+
+/// How many reshuffles are attempted before giving up on a puzzle whose
+/// unlocked tiles are all interchangeable
+const MAX_SHUFFLE_ATTEMPTS: usize = 100;
+
+/// The most cells a board can have along either side
+pub const MAX_BOARD_SIZE: usize = 40;
+
+/// How many puzzles are generated looking for one whose estimated difficulty
+/// is close to the target
+const MAX_GENERATE_ATTEMPTS: u64 = 10;
+
+/// How far the color of a saved tile may be from the one its settings give
+/// it, which allows for rounding in older versions
+const SAVED_COLOR_ERROR: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PuzzleSettings {
     difficulty: usize,
     width: usize,
     height: usize,
     gradient: Gradient,
     locking_pattern: LockingPattern,
+    shuffle: ShuffleStrategy,
     shuffle_seed: u64,
+    color_tolerance: f64,
+    /// Locked tiles are drawn like any other tile
+    hidden_locks: bool,
+    moves: MoveKind,
+}
+
+/// The kind of move a board is played with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MoveKind {
+    /// Any two unlocked tiles trade places
+    Swaps,
+    /// Unlocked tiles only trade places with the ones next to them
+    AdjacentSwaps,
+    /// A whole row or column rotates by one, rows and columns with a locked
+    /// tile in them can't be shifted
+    Shifts,
+    /// A whole row or column rotates by one, skipping over its locked tiles
+    ShiftsAroundLocks,
+}
+
+/// A row or column of the board
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+}
+
+/// A single move made on a board
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Move {
+    /// The tiles at the two indexes trade places
+    Swap(usize, usize),
+    /// Every tile of the line moves one cell along, the last one wrapping
+    /// around to the start. Forwards moves tiles right or down.
+    Shift { line: Line, forwards: bool },
+    /// The tile in each of the cells moves to the next one, the last one
+    /// wrapping around to the first
+    Cycle(Vec<usize>),
+}
+
+/// Why a set of settings can't make a puzzle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingsError {
+    /// The board has less than two cells
+    TooSmall,
+    /// The board is wider or taller than `MAX_BOARD_SIZE`
+    TooBig,
+    /// Less than two tiles can be moved
+    TooFewUnlocked,
+    /// A gradient corner isn't a real color
+    InvalidColor,
+    /// The color tolerance is negative or not a number
+    InvalidTolerance,
+    /// The tiles of a saved board don't match its settings
+    MismatchedTiles,
+    /// The board is played with shifts but every row and column is stuck
+    NoShiftableLines,
+    /// The board is played with adjacent swaps but no two unlocked tiles are
+    /// next to each other
+    NoAdjacentTiles,
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooSmall => write!(f, "The board needs at least two tiles"),
+            Self::TooBig => write!(
+                f,
+                "The board can be at most {} tiles across",
+                MAX_BOARD_SIZE
+            ),
+            Self::TooFewUnlocked => write!(f, "At least two tiles need to be unlocked"),
+            Self::InvalidColor => write!(f, "The gradient has an invalid color"),
+            Self::InvalidTolerance => write!(f, "The color tolerance is invalid"),
+            Self::MismatchedTiles => write!(f, "The tiles don't match the board"),
+            Self::NoShiftableLines => write!(f, "No row or column can be shifted"),
+            Self::NoAdjacentTiles => write!(f, "No two unlocked tiles are next to each other"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Puzzle {
     pub settings: PuzzleSettings,
+    pub stats: LevelStats,
     grid: Grid<PuzzleCell>,
+    history: History,
+    par: usize,
+    /// The moves that scrambled a board played with shifts or adjacent swaps,
+    /// its solution can be worked out by undoing them
+    scramble: Vec<Move>,
+    /// Tiles lock themselves once they're put in place
+    auto_lock: bool,
+    /// The solution as it was last worked out, hints ask for it after every
+    /// move and searching for it again can take a while. Cleared whenever a
+    /// tile moves.
+    #[serde(skip)]
+    solution: RefCell<Option<Vec<Move>>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Moves made by the player
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct History {
+    undo: Vec<Move>,
+    redo: Vec<Move>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PuzzleCell {
     pub solved_position: usize,
-    pub is_locked: bool,
+    pub lock: Option<Lock>,
     pub color: Color,
 }
 
+/// Why a tile can't be moved
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Lock {
+    /// Locked by the locking pattern of the board
+    Anchor,
+    /// Locked by the auto-lock assist after being put in place
+    Earned,
+}
+
 impl Puzzle {
-    pub fn generate_lvl1(win_size: (usize, usize)) -> Self {
+    pub fn generate_lvl1(profile: &DifficultyProfile, win_size: (usize, usize)) -> Self {
         let difficulty = debug::starting_difficulty().unwrap_or(1);
-        let settings = PuzzleSettings::from_difficulty(difficulty, win_size);
-        Self::from_settings(settings)
+        Self::generate(profile, difficulty, random(), win_size)
+    }
+
+    pub fn generate(
+        profile: &DifficultyProfile,
+        difficulty: usize,
+        seed: u64,
+        win_size: (usize, usize),
+    ) -> Self {
+        let puzzle = Self::generate_shuffled(profile, difficulty, seed, win_size);
+        if debug::disable_shuffle() {
+            Self::unshuffled(puzzle.settings)
+        } else {
+            puzzle
+        }
     }
 
-    pub fn next_level(&mut self, win_size: (usize, usize)) {
+    pub fn next_level(&mut self, profile: &DifficultyProfile, win_size: (usize, usize)) {
         let difficulty = self.settings.difficulty + 1;
-        let settings = PuzzleSettings::from_difficulty(difficulty, win_size);
-        *self = Self::from_settings(settings);
+        *self = Self::generate(profile, difficulty, random(), win_size);
+    }
+
+    /// The same board with a fresh shuffle
+    pub fn reshuffled(&self) -> Self {
+        let mut settings = self.settings.clone();
+        settings.shuffle_seed = random();
+        Self::from_settings(settings)
+    }
+
+    /// The board as it was before any moves were made. Hints and peeks stay
+    /// used up, so restarting doesn't hand out any more of them.
+    pub fn restarted(&self) -> Self {
+        let mut puzzle = self.clone();
+        while let Some(mv) = puzzle.history.undo.pop() {
+            puzzle.apply(&mv.inverse());
+        }
+        puzzle.history = History::default();
+        puzzle.stats = LevelStats {
+            hints_used: self.stats.hints_used,
+            peeks_used: self.stats.peeks_used,
+            ..LevelStats::default()
+        };
+        puzzle.update_earned_locks();
+        puzzle
     }
 
-    fn from_settings(settings: PuzzleSettings) -> Self {
+    /// Generates puzzles until one has an estimated difficulty close to the
+    /// target for its difficulty, falling back to the closest one.
+    /// The first attempt uses the seed as is and later ones derive theirs
+    /// from it, so the same seed still always generates the same puzzle.
+    fn generate_shuffled(
+        profile: &DifficultyProfile,
+        difficulty: usize,
+        seed: u64,
+        win_size: (usize, usize),
+    ) -> Self {
+        let target = profile.target_estimate(difficulty);
+        let mut closest: Option<(Self, f64)> = None;
+        for attempt in 0..MAX_GENERATE_ATTEMPTS {
+            let seed = seed ^ attempt.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+            let settings = PuzzleSettings::from_difficulty(profile, difficulty, seed, win_size);
+            let puzzle = Self::shuffled(settings);
+
+            let estimate = DifficultyEstimate::measure(&puzzle);
+            log::debug!("Difficulty {} estimated at {}", difficulty, estimate);
+            let error = (estimate.total() - target).abs();
+            if error <= difficulty::TOLERANCE {
+                return puzzle;
+            }
+            if closest.as_ref().is_none_or(|(_, closest)| error < *closest) {
+                closest = Some((puzzle, error));
+            }
+        }
+        closest.unwrap().0
+    }
+
+    /// Loads a saved puzzle, migrating saves from older versions
+    pub fn load(key: &str) -> Option<Self> {
+        savegame::load_versioned(key, SAVE_VERSION, legacy::migrate)
+    }
+
+    pub fn save(&self, key: &str) {
+        savegame::save_versioned(key, SAVE_VERSION, self);
+    }
+
+    pub fn from_share_code(code: &str) -> Result<Self, ShareCodeError> {
+        share_code::decode(code).map(Self::from_settings)
+    }
+
+    pub fn share_code(&self) -> String {
+        share_code::encode(&self.settings)
+    }
+
+    pub fn from_settings(settings: PuzzleSettings) -> Self {
+        if debug::disable_shuffle() {
+            Self::unshuffled(settings)
+        } else {
+            Self::shuffled(settings)
+        }
+    }
+
+    /// Creates the puzzle shuffled, whatever the debug options say
+    pub fn shuffled(settings: PuzzleSettings) -> Self {
+        let mut puzzle = Self::unshuffled(settings);
+        puzzle.shuffle();
+        puzzle.par = puzzle.solution().len();
+        puzzle
+    }
+
+    /// Creates the puzzle in its solved state
+    pub fn unshuffled(settings: PuzzleSettings) -> Self {
         let PuzzleSettings { width, height, .. } = settings;
+        let lock_mask = settings.lock_mask();
 
         let grid = Grid::from_closure(width, height, |x, y| PuzzleCell {
             solved_position: y * width + x,
-            is_locked: settings.is_cell_locked(x, y),
+            lock: if lock_mask.get(x, y) {
+                Some(Lock::Anchor)
+            } else {
+                None
+            },
             color: settings.get_cell_color(x, y),
         });
 
-        let mut puzzle = Self { settings, grid };
-
-        if !debug::disable_shuffle() {
-            puzzle.shuffle();
+        Self {
+            settings,
+            stats: LevelStats::default(),
+            grid,
+            history: History::default(),
+            par: 0,
+            scramble: Vec::new(),
+            auto_lock: false,
+            solution: RefCell::default(),
         }
-
-        puzzle
     }
 
     fn shuffle(&mut self) {
+        match self.settings.moves {
+            MoveKind::Swaps => {}
+            MoveKind::AdjacentSwaps => return self.scramble_neighbours(),
+            MoveKind::Shifts | MoveKind::ShiftsAroundLocks => return self.scramble_lines(),
+        }
+
         let unlocked_tiles = self
             .grid
             .iter()
             .enumerate()
-            .filter_map(|(idx, cell)| if cell.is_locked { None } else { Some(idx) })
+            .filter_map(|(idx, cell)| if cell.is_locked() { None } else { Some(idx) })
             .collect::<Vec<usize>>();
-        let mut shuffled = unlocked_tiles.clone();
+        let solved = self.grid.clone();
+
+        for attempt in 0..MAX_SHUFFLE_ATTEMPTS {
+            // the seed in the settings is left untouched so that the same
+            // settings always reproduce the same shuffle
+            let seed = self.settings.shuffle_seed.wrapping_add(attempt as u64);
+            let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(seed);
+
+            // strategies that can't scramble the board (like row shuffles on
+            // a board with one unlocked tile per row) fall back to random
+            let strategy = if attempt < MAX_SHUFFLE_ATTEMPTS / 2 {
+                self.settings.shuffle
+            } else {
+                ShuffleStrategy::Random
+            };
+            // every attempt starts over from the solved board
+            let (width, height) = solved.dims();
+            let sources = strategy.arrange(&unlocked_tiles, (width, height), &mut rng);
+            self.grid = Grid::from_closure(width, height, |x, y| {
+                solved.get(sources[y * width + x]).clone()
+            });
+
+            // If the shuffled puzzle is solved, reshuffled with the next seed
+            if !self.is_solved() {
+                return;
+            }
+        }
+    }
 
-        shuffled.shuffle(&mut rand_pcg::Pcg64Mcg::seed_from_u64(
-            self.settings.shuffle_seed,
-        ));
+    /// Shuffles a board played with shifts by making random shifts, as a
+    /// random arrangement of its tiles usually can't be solved with them
+    fn scramble_lines(&mut self) {
+        let lines = self.settings.shiftable_lines();
+        let shifts = (lines.len() * SCRAMBLE_SHIFTS_PER_LINE).max(MIN_SCRAMBLE_SHIFTS);
+        for attempt in 0..MAX_SHUFFLE_ATTEMPTS {
+            let seed = self.settings.shuffle_seed.wrapping_add(attempt as u64);
+            let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(seed);
+            for _ in 0..shifts {
+                let shift = Move::Shift {
+                    line: *lines.choose(&mut rng).unwrap(),
+                    forwards: rng.gen(),
+                };
+                self.apply(&shift);
+                self.scramble.push(shift);
+            }
+            if !self.is_solved() {
+                return;
+            }
+        }
+    }
 
-        for (original_position, shuffled_position) in unlocked_tiles.into_iter().zip(shuffled) {
-            self.grid.swap(original_position, shuffled_position);
+    /// Shuffles a board played with adjacent swaps by swapping random
+    /// neighbours, so that tiles walled in by locks stay where they can get
+    /// back from
+    fn scramble_neighbours(&mut self) {
+        let pairs = self.settings.adjacent_pairs();
+        let unlocked = self.iter().filter(|cell| !cell.is_locked()).count();
+        for attempt in 0..MAX_SHUFFLE_ATTEMPTS {
+            let seed = self.settings.shuffle_seed.wrapping_add(attempt as u64);
+            let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(seed);
+            for _ in 0..unlocked * SCRAMBLE_SWAPS_PER_TILE {
+                let (a, b) = *pairs.choose(&mut rng).unwrap();
+                self.apply(&Move::Swap(a, b));
+                self.scramble.push(Move::Swap(a, b));
+            }
+            if !self.is_solved() {
+                return;
+            }
         }
+    }
 
-        // If the shuffled puzzle is solved, reshuffled with the next seed
-        if self.is_solved() {
-            self.settings.shuffle_seed += 1;
-            self.shuffle();
+    /// Checks a puzzle that was loaded from a save, which may be from an
+    /// older version or have been tampered with. Every tile has to be one of
+    /// the board's, with the color and anchor its settings give it, and
+    /// anchors have to be where they belong.
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        self.settings.validate()?;
+        let PuzzleSettings { width, height, .. } = self.settings;
+        let cells = width * height;
+        let lock_mask = self.settings.lock_mask();
+        let mut seen = vec![false; cells];
+        let is_permutation = self.grid.dims() == (width, height)
+            && self.iter().all(|cell| {
+                cell.solved_position < cells
+                    && !std::mem::replace(&mut seen[cell.solved_position], true)
+            });
+        let matches_settings = || {
+            self.iter().enumerate().all(|(index, cell)| {
+                let (x, y) = (cell.solved_position % width, cell.solved_position / width);
+                let color = self.settings.get_cell_color(x, y);
+                let is_anchor = lock_mask.get(x, y);
+                cell.is_anchor() == is_anchor
+                    && (!is_anchor || index == cell.solved_position)
+                    && cell.color.distance(color) <= SAVED_COLOR_ERROR
+            })
+        };
+        if is_permutation && matches_settings() {
+            Ok(())
+        } else {
+            Err(SettingsError::MismatchedTiles)
         }
     }
 
@@ -93,88 +462,620 @@ impl Puzzle {
         self.grid.get(index)
     }
 
-    pub fn swap(&mut self, a: usize, b: usize) {
-        self.grid.swap(a, b)
+    /// Makes the move if the board allows it, returns false if it doesn't
+    pub fn make_move(&mut self, mv: Move) -> bool {
+        if !self.can_make(&mv) {
+            return false;
+        }
+        let cost = mv.cost();
+        self.apply(&mv);
+        self.history.undo.push(mv);
+        self.history.redo.clear();
+        self.stats.moves += cost;
+        if self.update_earned_locks() {
+            self.stats.auto_locked = true;
+        }
+        self.note_assisted_solve();
+        true
+    }
+
+    fn can_make(&self, mv: &Move) -> bool {
+        match *mv {
+            Move::Swap(a, b) => {
+                let (width, height) = self.dimensions();
+                let cells = width * height;
+                let is_adjacent = (a.max(b) - a.min(b) == 1 && a / width == b / width)
+                    || a.max(b) - a.min(b) == width;
+                let allowed = match self.settings.moves {
+                    MoveKind::Swaps => true,
+                    MoveKind::AdjacentSwaps => is_adjacent,
+                    MoveKind::Shifts | MoveKind::ShiftsAroundLocks => false,
+                };
+                allowed
+                    && a != b
+                    && a < cells
+                    && b < cells
+                    && !self.get(a).is_locked()
+                    && !self.get(b).is_locked()
+            }
+            Move::Shift { line, .. } => self.shifted_cells(line).is_some(),
+            Move::Cycle(ref cells) => {
+                let (width, height) = self.dimensions();
+                let mut seen = vec![false; width * height];
+                self.settings.moves == MoveKind::Swaps
+                    && cells.len() >= 2
+                    && cells.iter().all(|&cell| {
+                        cell < seen.len()
+                            && !std::mem::replace(&mut seen[cell], true)
+                            && !self.get(cell).is_locked()
+                    })
+            }
+        }
+    }
+
+    /// Moves the tiles without any checks or bookkeeping other than
+    /// forgetting the solution
+    fn apply(&mut self, mv: &Move) {
+        self.solution.get_mut().take();
+        match *mv {
+            Move::Swap(a, b) => self.grid.swap(a, b),
+            Move::Shift { line, forwards } => {
+                let mut cells = self.shifted_cells(line).unwrap_or_default();
+                if !forwards {
+                    cells.reverse();
+                }
+                self.rotate(&cells);
+            }
+            Move::Cycle(ref cells) => self.rotate(cells),
+        }
+    }
+
+    /// Moves the tile in each of the cells to the next one, and the last one
+    /// to the first
+    fn rotate(&mut self, cells: &[usize]) {
+        for i in (1..cells.len()).rev() {
+            self.grid.swap(cells[i], cells[i - 1]);
+        }
+    }
+
+    /// The cells whose tiles move when the line is shifted, or None if the
+    /// board can't shift it
+    pub fn shifted_cells(&self, line: Line) -> Option<Vec<usize>> {
+        self.settings
+            .shifted_cells(line, &self.settings.lock_mask())
+    }
+
+    /// The move that undoing takes back, which costs as much as making it
+    pub fn next_undo(&self) -> Option<&Move> {
+        self.history.undo.last()
+    }
+
+    /// The move that redoing makes again
+    pub fn next_redo(&self) -> Option<&Move> {
+        self.history.redo.last()
+    }
+
+    /// Reverts the last move, returns false if there was nothing to undo
+    pub fn undo(&mut self) -> bool {
+        if let Some(mv) = self.history.undo.pop() {
+            self.apply(&mv.inverse());
+            self.stats.moves += mv.cost();
+            self.history.redo.push(mv);
+            self.update_earned_locks();
+            self.note_assisted_solve();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reapplies the last undone move, returns false if there was nothing to redo
+    pub fn redo(&mut self) -> bool {
+        if let Some(mv) = self.history.redo.pop() {
+            self.apply(&mv);
+            self.stats.moves += mv.cost();
+            self.history.undo.push(mv);
+            self.update_earned_locks();
+            self.note_assisted_solve();
+            true
+        } else {
+            false
+        }
     }
 
     pub fn iter(&self) -> GridIter<PuzzleCell> {
         self.grid.iter()
     }
 
-    pub fn is_solved(&self) -> bool {
-        let mut iter = self.grid.iter();
-        let mut prev = iter.next().map(|cell| cell.solved_position);
-        for cell in iter {
-            let this = Some(cell.solved_position);
-            if prev > this {
-                return false;
+    /// Returns the moves that solve the puzzle
+    pub fn solution(&self) -> Vec<Move> {
+        self.solution
+            .borrow_mut()
+            .get_or_insert_with(|| self.solve())
+            .clone()
+    }
+
+    fn solve(&self) -> Vec<Move> {
+        let made = self.scramble.iter().chain(&self.history.undo);
+        match self.settings.moves {
+            MoveKind::Swaps => self.swap_solution(),
+            MoveKind::AdjacentSwaps => {
+                let tiles = self
+                    .iter()
+                    .map(|cell| cell.solved_position)
+                    .collect::<Vec<_>>();
+                let locked = self.iter().map(PuzzleCell::is_locked).collect::<Vec<_>>();
+                let (width, _) = self.dimensions();
+                // the search gives up on boards with too many ways to go,
+                // which are solved by undoing the moves made on them instead
+                adjacent_solver::solve(&tiles, width, &locked)
+                    .unwrap_or_else(|| adjacent_solver::undo_swaps(made))
+            }
+            MoveKind::Shifts | MoveKind::ShiftsAroundLocks => {
+                let tiles = self
+                    .iter()
+                    .map(|cell| cell.solved_position)
+                    .collect::<Vec<_>>();
+                let lines = self
+                    .settings
+                    .shiftable_lines()
+                    .into_iter()
+                    .filter_map(|line| Some((line, self.shifted_cells(line)?)))
+                    .collect::<Vec<_>>();
+                shift_solver::solve(&tiles, &lines, made)
+            }
+        }
+    }
+
+    /// Returns the swaps that solve the puzzle in as few moves as it can find.
+    ///
+    /// Tiles that are in place, including ones that only look like the tile
+    /// that belongs there, are left alone. Every swap moves a tile to a cell
+    /// it looks right in, preferring swaps that put both tiles in place, so
+    /// each cycle of misplaced tiles takes one less swap than its length.
+    fn swap_solution(&self) -> Vec<Move> {
+        let (width, height) = self.dimensions();
+        let cells = width * height;
+        let tolerance = self.settings.color_tolerance;
+        let solved_colors = (0..cells)
+            .map(|cell| {
+                let color = self.settings.get_cell_color(cell % width, cell / width);
+                color.to_lab()
+            })
+            .collect::<Vec<_>>();
+        let fits = |(home, color): (usize, (f64, f64, f64)), cell: usize| {
+            home == cell || gradient::lab_distance(color, solved_colors[cell]) <= tolerance
+        };
+
+        let mut tiles = self
+            .iter()
+            .map(|cell| (cell.solved_position, cell.color.to_lab()))
+            .collect::<Vec<_>>();
+        let mut in_place = (0..cells)
+            .map(|cell| fits(tiles[cell], cell))
+            .collect::<Vec<_>>();
+
+        let mut swaps = Vec::new();
+        for index in 0..cells {
+            while !in_place[index] {
+                let tile = tiles[index];
+                let targets = || {
+                    (0..cells).filter(|&cell| cell != index && !in_place[cell] && fits(tile, cell))
+                };
+                let (home, _) = tile;
+                let target = targets()
+                    .find(|&cell| fits(tiles[cell], index))
+                    .or_else(|| Some(home).filter(|&home| !in_place[home]))
+                    .or_else(|| targets().next())
+                    // tolerances don't chain, so a tile whose home only looks
+                    // right with another tile in it can have nowhere else to go
+                    .unwrap_or(home);
+                swaps.push(Move::Swap(index, target));
+                tiles.swap(index, target);
+                in_place[index] = fits(tiles[index], index);
+                in_place[target] = true;
+            }
+        }
+        swaps
+    }
+
+    /// The number of moves the solution had right after being shuffled, which
+    /// is optimal for boards played with swaps whose tiles all look different
+    /// and for adjacent swaps on boards small enough to search. Boards played
+    /// with shifts only get an upper bound, as their solution mostly undoes
+    /// the scramble.
+    pub fn par(&self) -> usize {
+        self.par
+    }
+
+    pub fn stars(&self) -> usize {
+        // working out which tiles are anchors costs a few extra moves
+        let leeway = if self.settings.hidden_locks {
+            self.par / 4 + 1
+        } else {
+            0
+        };
+        stats::star_rating(self.stats.moves, self.par + leeway)
+    }
+
+    /// Whether the tile is shown with a lock, anchors look like any other
+    /// tile while locks are hidden
+    pub fn shows_lock(&self, cell: &PuzzleCell) -> bool {
+        cell.is_locked() && !(cell.is_anchor() && self.settings.hidden_locks)
+    }
+
+    /// Hiding the locks changes the rating of the board, so it can only be
+    /// done before it's started
+    pub fn set_hidden_locks(&mut self, hidden: bool) {
+        if self.stats.moves == 0 {
+            self.settings.hidden_locks = hidden;
+        }
+    }
+
+    pub fn auto_lock(&self) -> bool {
+        self.auto_lock
+    }
+
+    /// Turns the auto-lock assist on or off. It only locks tiles on boards
+    /// played with swaps, where a tile that's in place never has to move again.
+    pub fn set_auto_lock(&mut self, on: bool) {
+        self.auto_lock = on;
+        self.update_earned_locks();
+    }
+
+    /// Whether the auto-lock assist is on and applies to the board
+    fn is_assisted(&self) -> bool {
+        self.auto_lock && self.settings.moves == MoveKind::Swaps
+    }
+
+    /// Locks the tiles that are in place while the assist is on, and unlocks
+    /// the ones that were moved away by undoing or that it no longer applies to.
+    /// Returns whether any tile was newly locked.
+    fn update_earned_locks(&mut self) -> bool {
+        let assist = self.is_assisted();
+        let (width, height) = self.dimensions();
+        let mut newly_locked = false;
+        for index in 0..width * height {
+            let cell = self.get(index);
+            if cell.is_anchor() {
+                continue;
             }
-            prev = this;
+            let earned = assist && self.is_in_place(index, cell);
+            newly_locked |= earned && cell.lock.is_none();
+            self.grid.get_mut(index).lock = if earned { Some(Lock::Earned) } else { None };
         }
+        newly_locked
+    }
+
+    /// A board finished with the assist on counts as helped by it, even if
+    /// the last move didn't lock anything new
+    fn note_assisted_solve(&mut self) {
+        if self.is_assisted() && self.is_solved() {
+            self.stats.auto_locked = true;
+        }
+    }
+
+    /// Counts a peek at the solved board, returns false if the limit (if
+    /// there is one) has been used up
+    pub fn use_peek(&mut self, limit: Option<usize>) -> bool {
+        if limit.is_some_and(|limit| self.stats.peeks_used >= limit) {
+            return false;
+        }
+        self.stats.peeks_used += 1;
         true
     }
+
+    /// How many more moves can be made, if they're limited
+    pub fn moves_remaining(&self, limit: Option<usize>) -> Option<usize> {
+        limit.map(|limit| limit.saturating_sub(self.stats.moves))
+    }
+
+    /// Whether the limit (if there is one) has been reached, or gone past
+    pub fn is_out_of_moves(&self, limit: Option<usize>) -> bool {
+        limit.is_some_and(|limit| self.stats.moves >= limit)
+    }
+
+    /// Whether the move fits in the moves that are left, if they're limited
+    pub fn can_afford(&self, mv: &Move, limit: Option<usize>) -> bool {
+        self.moves_remaining(limit)
+            .is_none_or(|remaining| remaining >= mv.cost())
+    }
+
+    /// How many more hints can be used, if they're limited
+    pub fn hints_remaining(&self, limit: Option<usize>) -> Option<usize> {
+        limit.map(|limit| limit.saturating_sub(self.stats.hints_used))
+    }
+
+    /// Uses up one of the level's hints and returns the next move of the
+    /// solution, or None if the limit (if there is one) has been used up.
+    /// The tile at the first index of a swap belongs where the second one is.
+    pub fn use_hint(&mut self, limit: Option<usize>) -> Option<Move> {
+        if self.hints_remaining(limit) == Some(0) {
+            return None;
+        }
+        let hint = self.solution().into_iter().next()?;
+        self.stats.hints_used += 1;
+        Some(hint)
+    }
+
+    /// A puzzle is solved once every tile is either in its solved position or
+    /// looks the same as the tile that belongs there
+    pub fn is_solved(&self) -> bool {
+        self.grid
+            .iter()
+            .enumerate()
+            .all(|(index, cell)| self.is_in_place(index, cell))
+    }
+
+    /// How many of the tiles that aren't anchors are where they belong
+    pub fn correctly_placed(&self) -> usize {
+        self.grid
+            .iter()
+            .enumerate()
+            .filter(|(index, cell)| !cell.is_anchor() && self.is_in_place(*index, cell))
+            .count()
+    }
+
+    fn is_in_place(&self, index: usize, cell: &PuzzleCell) -> bool {
+        let (width, _) = self.dimensions();
+        cell.solved_position == index || {
+            let solved_color = self.settings.get_cell_color(index % width, index / width);
+            cell.color.distance(solved_color) <= self.settings.color_tolerance
+        }
+    }
+}
+
+impl PuzzleCell {
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    pub fn is_anchor(&self) -> bool {
+        self.lock == Some(Lock::Anchor)
+    }
 }
 
 impl PuzzleSettings {
-    fn from_difficulty(difficulty: usize, win_size: (usize, usize)) -> Self {
-        let (width, height) = generate_puzzle_size(difficulty, win_size);
-        Self {
-            difficulty,
+    /// Settings for a hand made puzzle, these aren't part of the difficulty
+    /// progression so they have a difficulty of 0
+    pub fn custom(
+        width: usize,
+        height: usize,
+        gradient: Gradient,
+        locking_pattern: LockingPattern,
+        moves: MoveKind,
+    ) -> Result<Self, SettingsError> {
+        let settings = Self {
+            difficulty: 0,
             width,
             height,
-            gradient: generate_gradient(difficulty),
-            locking_pattern: generate_locking_pattern(difficulty),
+            gradient,
+            locking_pattern,
+            shuffle: ShuffleStrategy::Random,
             shuffle_seed: random(),
+            color_tolerance: DEFAULT_COLOR_TOLERANCE,
+            hidden_locks: false,
+            moves,
+        };
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Checks that the settings make a puzzle that can be played
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.width > MAX_BOARD_SIZE || self.height > MAX_BOARD_SIZE {
+            return Err(SettingsError::TooBig);
+        }
+        if self.width * self.height < 2 {
+            return Err(SettingsError::TooSmall);
+        }
+
+        let is_valid_color = |color: &Color| match *color {
+            Color::RGB { .. } => true,
+            Color::HSL { h, s, l } => {
+                h.is_finite() && (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&l)
+            }
+        };
+        if !self.gradient.corners().iter().all(is_valid_color) {
+            return Err(SettingsError::InvalidColor);
+        }
+        if !(self.color_tolerance.is_finite() && self.color_tolerance >= 0.0) {
+            return Err(SettingsError::InvalidTolerance);
+        }
+
+        let lock_mask = self.lock_mask();
+        let unlocked = (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !lock_mask.get(x, y))
+            .count();
+        if unlocked < 2 {
+            return Err(SettingsError::TooFewUnlocked);
+        }
+        if self.moves.shifts_lines() && self.shiftable_lines().is_empty() {
+            return Err(SettingsError::NoShiftableLines);
+        }
+        if self.moves == MoveKind::AdjacentSwaps && self.adjacent_pairs().is_empty() {
+            return Err(SettingsError::NoAdjacentTiles);
+        }
+        Ok(())
+    }
+
+    /// The cells whose tiles move when the line is shifted, or None if the
+    /// line can't be shifted
+    fn shifted_cells(&self, line: Line, lock_mask: &LockMask) -> Option<Vec<usize>> {
+        let width = self.width;
+        let cells = match line {
+            Line::Row(y) if y < self.height => (0..width).map(|x| y * width + x).collect(),
+            Line::Column(x) if x < width => (0..self.height).map(|y| y * width + x).collect(),
+            _ => Vec::new(),
+        };
+        let unlocked = cells
+            .iter()
+            .cloned()
+            .filter(|&i| !lock_mask.get(i % width, i / width))
+            .collect::<Vec<usize>>();
+        let blocked = self.moves == MoveKind::Shifts && unlocked.len() < cells.len();
+        if !self.moves.shifts_lines() || blocked || unlocked.len() < 2 {
+            None
+        } else {
+            Some(unlocked)
+        }
+    }
+
+    /// Every pair of unlocked cells next to each other
+    fn adjacent_pairs(&self) -> Vec<(usize, usize)> {
+        let lock_mask = self.lock_mask();
+        let locked = (0..self.width * self.height)
+            .map(|i| lock_mask.get(i % self.width, i / self.width))
+            .collect::<Vec<_>>();
+        adjacent_solver::neighbours(locked.len(), self.width, &locked)
+    }
+
+    fn shiftable_lines(&self) -> Vec<Line> {
+        let lock_mask = self.lock_mask();
+        (0..self.height)
+            .map(Line::Row)
+            .chain((0..self.width).map(Line::Column))
+            .filter(|&line| self.shifted_cells(line, &lock_mask).is_some())
+            .collect()
+    }
+
+    pub fn hidden_locks(&self) -> bool {
+        self.hidden_locks
+    }
+
+    pub fn difficulty(&self) -> usize {
+        self.difficulty
+    }
+
+    pub fn moves(&self) -> MoveKind {
+        self.moves
+    }
+
+    /// Generates the settings for a puzzle of the given difficulty.
+    /// All randomness comes from the seed, so the same difficulty, seed and
+    /// window aspect ratio always generate the same puzzle.
+    fn from_difficulty(
+        profile: &DifficultyProfile,
+        difficulty: usize,
+        seed: u64,
+        win_size: (usize, usize),
+    ) -> Self {
+        let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(seed);
+        let (width, height) = generate_puzzle_size(profile, difficulty, win_size);
+        let gradient = generate_gradient(profile, difficulty, &mut rng);
+        let locking_pattern =
+            locking_pattern::generate(profile, difficulty, (width, height), &mut rng);
+        let shuffle_seed = rng.gen();
+
+        let lock_mask = locking_pattern.mask(width, height);
+        let unlocked = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !lock_mask.get(x, y))
+            .count();
+        let progress = profile.progress(difficulty);
+        let mut settings = Self {
+            difficulty,
+            width,
+            height,
+            gradient,
+            locking_pattern,
+            shuffle: ShuffleStrategy::generate(progress, unlocked, &mut rng),
+            shuffle_seed,
+            color_tolerance: DEFAULT_COLOR_TOLERANCE,
+            hidden_locks: false,
+            moves: MoveKind::Swaps,
+        };
+        // the size is clamped and the colors are always real, so only the
+        // locks can leave a board that can't be played. Rather than crash,
+        // the board is played without them.
+        if let Err(err) = settings.validate() {
+            log::warn!("Generated invalid settings ({}): {:?}", err, settings);
+            settings.locking_pattern = LockingPattern::Custom(LockMask::new(1, 1));
         }
+        settings
+    }
+
+    pub fn lock_mask(&self) -> LockMask {
+        self.locking_pattern.mask(self.width, self.height)
     }
 
     fn get_cell_color(&self, x: usize, y: usize) -> Color {
-        let x_off = x as f64 / (self.width as f64 - 1.0);
-        let y_off = y as f64 / (self.height as f64 - 1.0);
-        self.gradient.color_at(Position::new(x_off, y_off))
-    }
-
-    fn is_cell_locked(&self, x: usize, y: usize) -> bool {
-        use LockingPattern::*;
-
-        let is_corner = || (x == 0 || x == (self.width - 1)) && (y == 0 || y == (self.height - 1));
-        let is_border = || x == 0 || x == (self.width - 1) || y == 0 || y == (self.height - 1);
-        let is_checkboard = || (x + y) % 2 == 0;
-        let is_shortlines = || (if self.width > self.height { x } else { y }) % 2 == 0;
-        let is_longlines = || (if self.width < self.height { x } else { y }) % 2 == 0;
-
-        match self.locking_pattern {
-            Corners => is_corner(),
-            Borders => is_border(),
-            ReverseBorders => !is_border(),
-            CheckerboardA => is_checkboard(),
-            CheckerboardB => !is_checkboard(),
-            HalfCheckerboardA => x % 2 == 0 && y % 2 == 0,
-            HalfCheckerboardB => x % 2 != 0 && y % 2 != 0,
-            ShortLinesA => is_shortlines(),
-            ShortLinesB => !is_shortlines(),
-            LongLinesA => is_longlines(),
-            LongLinesB => !is_longlines(),
+        let position = cell_position(x, y, self.width, self.height);
+        self.gradient.color_at(position)
+    }
+}
+
+impl MoveKind {
+    pub const ALL: [MoveKind; 4] = [
+        MoveKind::Swaps,
+        MoveKind::AdjacentSwaps,
+        MoveKind::Shifts,
+        MoveKind::ShiftsAroundLocks,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveKind::Swaps => "Swaps",
+            MoveKind::AdjacentSwaps => "Adjacent Swaps",
+            MoveKind::Shifts => "Shifts",
+            MoveKind::ShiftsAroundLocks => "Shifts Around Locks",
         }
     }
+
+    /// Whether the board is played by shifting rows and columns
+    pub fn shifts_lines(self) -> bool {
+        matches!(self, MoveKind::Shifts | MoveKind::ShiftsAroundLocks)
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
-enum LockingPattern {
-    Corners,
-    Borders,
-    ReverseBorders,
-    CheckerboardA,
-    CheckerboardB,
-    HalfCheckerboardA,
-    HalfCheckerboardB,
-    ShortLinesA,
-    ShortLinesB,
-    LongLinesA,
-    LongLinesB,
+impl Move {
+    /// Moves the tile in each of the cells to the next one, a swap if there
+    /// are only two of them
+    pub fn rotation(cells: Vec<usize>) -> Self {
+        match cells[..] {
+            [a, b] => Move::Swap(a, b),
+            _ => Move::Cycle(cells),
+        }
+    }
+
+    /// The move that takes this one back
+    pub fn inverse(&self) -> Self {
+        match *self {
+            Move::Swap(a, b) => Move::Swap(a, b),
+            Move::Shift { line, forwards } => Move::Shift {
+                line,
+                forwards: !forwards,
+            },
+            Move::Cycle(ref cells) => Move::Cycle(cells.iter().rev().cloned().collect()),
+        }
+    }
+
+    /// How many moves it counts as. Cycling k tiles counts as the k - 1 swaps
+    /// it takes to do the same, so it's only quicker, not cheaper.
+    pub fn cost(&self) -> usize {
+        match self {
+            Move::Swap(..) | Move::Shift { .. } => 1,
+            Move::Cycle(cells) => cells.len().saturating_sub(1),
+        }
+    }
+}
+
+/// Where the cell at `x, y` of a board takes its color from the gradient. A
+/// board that's a single cell wide or tall runs down the middle of it.
+pub fn cell_position(x: usize, y: usize, width: usize, height: usize) -> Position {
+    let offset = |v: usize, size: usize| {
+        if size > 1 {
+            v as f64 / (size as f64 - 1.0)
+        } else {
+            0.5
+        }
+    };
+    Position::new(offset(x, width), offset(y, height))
 }
 
 fn generate_puzzle_size(
+    profile: &DifficultyProfile,
     difficulty: usize,
     (win_width, win_height): (usize, usize),
 ) -> (usize, usize) {
@@ -183,11 +1084,13 @@ fn generate_puzzle_size(
     // the cells are square-ish
     //
     // X is based on the difficulty with higher difficulties creating bigger puzzles
-    const MIN_CELLS: usize = 5;
-    let short = MIN_CELLS + (difficulty as f64).log(4.0).powf(2.0).trunc() as usize;
+    let growth = (difficulty.max(1) as f64)
+        .log(profile.size_growth)
+        .powf(2.0);
+    let short = (profile.min_cells + growth.trunc() as usize).min(MAX_BOARD_SIZE);
 
     let ratio = win_width.max(win_height) as f64 / win_width.min(win_height) as f64;
-    let long = 2.max((short as f64 * ratio).round() as usize);
+    let long = ((short as f64 * ratio).round() as usize).clamp(2, MAX_BOARD_SIZE);
 
     if win_width < win_height {
         (short, long)
@@ -196,16 +1099,18 @@ fn generate_puzzle_size(
     }
 }
 
-fn generate_gradient(difficulty: usize) -> Gradient {
+fn generate_gradient<R: Rng>(
+    profile: &DifficultyProfile,
+    difficulty: usize,
+    rng: &mut R,
+) -> Gradient {
     // Higher difficulties create gradients with a lower hue variance
-    const MAX_HUE: f64 = 300.0;
-    const MIN_HUE: f64 = 90.0;
-    let hue_variance = (MAX_HUE - (difficulty - 1) as f64).max(MIN_HUE);
+    let hue_variance = (profile.max_hue_variance
+        - difficulty.saturating_sub(1) as f64 * profile.hue_variance_step)
+        .max(profile.min_hue_variance);
 
     debug_assert!(hue_variance > 0.0 && hue_variance <= 360.0);
 
-    let mut rng = thread_rng();
-
     let start_hue = rng.gen_range(0.0, 360.0);
     let diff = hue_variance / 4.0;
     let hues = [
@@ -215,23 +1120,25 @@ fn generate_gradient(difficulty: usize) -> Gradient {
         start_hue + diff * 3.0,
     ];
 
-    fn sample_ranged_normal(mean: f64, sd: f64, min: f64, max: f64) -> f64 {
-        let mut rng = thread_rng();
+    fn sample_ranged_normal<R: Rng>(rng: &mut R, range: NormalRange) -> f64 {
         loop {
-            let value = rng.sample(Normal::new(mean, sd).unwrap());
-            if value >= min && value <= max {
+            let value = rng.sample(Normal::new(range.mean, range.sd).unwrap());
+            if value >= range.min && value <= range.max {
                 return value;
             }
         }
     }
 
-    let gen_s = || sample_ranged_normal(0.9, 0.1, 0.5, 1.0);
-    let gen_l = || sample_ranged_normal(0.5, 0.005, 0.4, 0.7);
+    let mut gen_color = |hue| {
+        let s = sample_ranged_normal(rng, profile.saturation);
+        let l = sample_ranged_normal(rng, profile.lightness);
+        Color::hsl(hue, s, l)
+    };
 
-    let top_left = Color::hsl(hues[0], gen_s(), gen_l());
-    let top_right = Color::hsl(hues[1], gen_s(), gen_l());
-    let bottom_right = Color::hsl(hues[2], gen_s(), gen_l());
-    let bottom_left = Color::hsl(hues[3], gen_s(), gen_l());
+    let top_left = gen_color(hues[0]);
+    let top_right = gen_color(hues[1]);
+    let bottom_right = gen_color(hues[2]);
+    let bottom_left = gen_color(hues[3]);
 
     Gradient::builder()
         .top_left(top_left.to_rgb())
@@ -241,27 +1148,510 @@ fn generate_gradient(difficulty: usize) -> Gradient {
         .build()
 }
 
-fn generate_locking_pattern(_difficulty: usize) -> LockingPattern {
-    // generate a random locking pattern
-    // weighted roughly according to difficulty of the locking pattern
-    // harder patterns have a lower weight and are less likely to be selected
-    use LockingPattern::*;
-    const PATTERNS: [(LockingPattern, usize); 11] = [
-        (Corners, 1),
-        (Borders, 2),
-        (HalfCheckerboardA, 5),
-        (HalfCheckerboardB, 5),
-        (ShortLinesA, 6),
-        (ShortLinesB, 6),
-        (LongLinesA, 7),
-        (LongLinesB, 7),
-        (CheckerboardA, 7),
-        (CheckerboardB, 7),
-        (ReverseBorders, 2), // lower because this pattern is too easy
-    ];
-    lazy_static! {
-        static ref DISTRIBUTION: WeightedIndex<usize> =
-            WeightedIndex::new(PATTERNS.iter().map(|item| item.1)).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIN_SIZE: (usize, usize) = (1920, 1080);
+
+    fn standard() -> &'static DifficultyProfile {
+        DifficultyProfile::standard()
+    }
+
+    #[test]
+    fn same_seed_generates_same_puzzle() {
+        for difficulty in &[1, 10, 100, 500] {
+            for seed in 0..20 {
+                let a = PuzzleSettings::from_difficulty(standard(), *difficulty, seed, WIN_SIZE);
+                let b = PuzzleSettings::from_difficulty(standard(), *difficulty, seed, WIN_SIZE);
+                assert_eq!(a, b);
+
+                let a = Puzzle::shuffled(a);
+                let b = Puzzle::shuffled(b);
+                assert_eq!(a.settings, b.settings);
+                assert!(a.iter().zip(b.iter()).all(|(a, b)| a == b));
+            }
+        }
+    }
+
+    #[test]
+    fn different_seeds_generate_different_puzzles() {
+        let a = PuzzleSettings::from_difficulty(standard(), 1, 1, WIN_SIZE);
+        let b = PuzzleSettings::from_difficulty(standard(), 1, 2, WIN_SIZE);
+        assert_ne!(a.gradient, b.gradient);
+        assert_ne!(a.shuffle_seed, b.shuffle_seed);
+    }
+
+    #[test]
+    fn generated_puzzles_are_close_to_their_target_difficulty() {
+        for profile in DifficultyProfile::all() {
+            for difficulty in &[1, 10, 50, 200] {
+                let target = profile.target_estimate(*difficulty);
+                let close = (0..20)
+                    .filter(|&seed| {
+                        let puzzle =
+                            Puzzle::generate_shuffled(profile, *difficulty, seed, WIN_SIZE);
+                        let estimate = DifficultyEstimate::measure(&puzzle).total();
+                        (estimate - target).abs() <= difficulty::TOLERANCE
+                    })
+                    .count();
+                assert!(
+                    close >= 18,
+                    "{} difficulty {} only had {} close",
+                    profile.name,
+                    difficulty,
+                    close
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shuffled_puzzles_are_not_solved() {
+        for seed in 0..100 {
+            let settings =
+                PuzzleSettings::from_difficulty(standard(), seed as usize + 1, seed, WIN_SIZE);
+            let puzzle = Puzzle::shuffled(settings.clone());
+            assert!(!puzzle.is_solved());
+            assert!(Puzzle::unshuffled(settings).is_solved());
+            assert_eq!(puzzle.par(), puzzle.solution().len());
+        }
+    }
+
+    #[test]
+    fn solution_puts_every_tile_in_place() {
+        let settings =
+            PuzzleSettings::custom(4, 3, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        assert!(puzzle.solution().is_empty());
+
+        // a cycle of three tiles takes two swaps and a swapped pair one more
+        assert!(puzzle.make_move(Move::Cycle(vec![1, 2, 5])));
+        assert!(puzzle.make_move(Move::Swap(6, 9)));
+        let solution = puzzle.solution();
+        assert_eq!(solution.len(), 3);
+        for mv in solution {
+            assert!(puzzle.make_move(mv));
+        }
+        assert!(puzzle.is_solved());
+    }
+
+    #[test]
+    fn validate_settings() {
+        let gradient = |corner| {
+            Gradient::builder()
+                .top_left(corner)
+                .top_right(Color::rgb(255, 0, 0))
+                .bottom_left(Color::rgb(0, 255, 0))
+                .bottom_right(Color::rgb(0, 0, 255))
+                .build()
+        };
+        let custom = |width, height, pattern| {
+            let gradient = gradient(Color::rgb(0, 0, 0));
+            PuzzleSettings::custom(width, height, gradient, pattern, MoveKind::Swaps)
+        };
+
+        // strips take their colors from the middle of the gradient
+        for &(width, height) in &[(1, 6), (6, 1), (1, 2)] {
+            let settings = custom(width, height, LockingPattern::Custom(LockMask::new(1, 1)));
+            let puzzle = Puzzle::unshuffled(settings.unwrap());
+            assert_eq!(puzzle.validate(), Ok(()));
+            let colors = puzzle.iter().map(|cell| cell.color).collect::<Vec<_>>();
+            assert!(colors.windows(2).all(|pair| pair[0] != pair[1]));
+        }
+
+        let open = || LockingPattern::Custom(LockMask::new(1, 1));
+        assert_eq!(custom(1, 1, open()), Err(SettingsError::TooSmall));
+        assert_eq!(custom(0, 5, open()), Err(SettingsError::TooSmall));
+        let too_big = custom(MAX_BOARD_SIZE + 1, 2, open());
+        assert_eq!(too_big, Err(SettingsError::TooBig));
+        let locked = "#.#".parse().unwrap();
+        let locked = custom(3, 1, LockingPattern::Custom(locked));
+        assert_eq!(locked, Err(SettingsError::TooFewUnlocked));
+        let nan = Color::hsl(f64::NAN, 0.5, 0.5);
+        let invalid_color = PuzzleSettings::custom(4, 4, gradient(nan), open(), MoveKind::Swaps);
+        assert_eq!(invalid_color, Err(SettingsError::InvalidColor));
+        let mut settings = custom(4, 4, open()).unwrap();
+        settings.color_tolerance = f64::NAN;
+        assert_eq!(settings.validate(), Err(SettingsError::InvalidTolerance));
+
+        for &difficulty in &[0, 1_000_000] {
+            for profile in DifficultyProfile::all() {
+                for &win_size in &[(4000, 100), (1, 1)] {
+                    let settings =
+                        PuzzleSettings::from_difficulty(profile, difficulty, 0, win_size);
+                    assert!(settings.width <= MAX_BOARD_SIZE && settings.height <= MAX_BOARD_SIZE);
+                    assert_eq!(settings.validate(), Ok(()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn restored_boards_match_their_settings() {
+        let settings =
+            PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let puzzle = Puzzle::shuffled(settings.unwrap());
+        assert_eq!(puzzle.validate(), Ok(()));
+        let tampered = |change: &dyn Fn(&mut Puzzle)| {
+            let mut puzzle = puzzle.clone();
+            change(&mut puzzle);
+            puzzle.validate()
+        };
+
+        let mismatched = Err(SettingsError::MismatchedTiles);
+        assert_eq!(
+            tampered(&|puzzle| puzzle.grid.get_mut(0).lock = None),
+            mismatched
+        );
+        let unlocked = puzzle.iter().position(|cell| !cell.is_locked()).unwrap();
+        let anchor_elsewhere =
+            |puzzle: &mut Puzzle| puzzle.grid.get_mut(unlocked).lock = Some(Lock::Anchor);
+        assert_eq!(tampered(&anchor_elsewhere), mismatched);
+        assert_eq!(
+            tampered(&|puzzle| puzzle.grid.swap(0, unlocked)),
+            mismatched
+        );
+        let recolored =
+            |puzzle: &mut Puzzle| puzzle.grid.get_mut(unlocked).color = Color::rgb(1, 2, 3);
+        assert_eq!(tampered(&recolored), mismatched);
+        let relocked =
+            |puzzle: &mut Puzzle| puzzle.settings.locking_pattern = LockingPattern::Borders;
+        assert_eq!(tampered(&relocked), mismatched);
+
+        // locks earned with the auto-lock assist aren't part of the settings
+        let mut assisted = puzzle.clone();
+        assisted.set_auto_lock(true);
+        assert_eq!(assisted.validate(), Ok(()));
+    }
+
+    #[test]
+    fn hidden_locks_are_rated_leniently() {
+        let settings = PuzzleSettings::from_difficulty(standard(), 20, 0, WIN_SIZE);
+        let mut puzzle = Puzzle::shuffled(settings);
+        puzzle.set_hidden_locks(true);
+        assert!(puzzle.settings.hidden_locks());
+
+        puzzle.stats.moves = puzzle.par() + 1;
+        assert_eq!(puzzle.stars(), 3);
+        puzzle.settings.hidden_locks = false;
+        assert_eq!(puzzle.stars(), 2);
+
+        // a started board keeps its rating
+        puzzle.set_hidden_locks(true);
+        assert!(!puzzle.settings.hidden_locks());
+    }
+
+    #[test]
+    fn hidden_anchors_show_no_lock() {
+        let settings =
+            PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        puzzle.set_auto_lock(true);
+        assert!(puzzle.get(0).is_anchor() && puzzle.shows_lock(puzzle.get(0)));
+        assert_eq!(puzzle.get(1).lock, Some(Lock::Earned));
+        let mut cell = puzzle.get(1).clone();
+        cell.lock = None;
+        assert!(!puzzle.shows_lock(&cell));
+
+        puzzle.set_hidden_locks(true);
+        assert!(!puzzle.shows_lock(puzzle.get(0)));
+        assert!(puzzle.shows_lock(puzzle.get(1)));
+    }
+
+    fn rainbow() -> Gradient {
+        Gradient::builder()
+            .top_left(Color::rgb(255, 0, 0))
+            .top_right(Color::rgb(255, 255, 0))
+            .bottom_left(Color::rgb(0, 0, 255))
+            .bottom_right(Color::rgb(0, 255, 0))
+            .build()
+    }
+
+    /// Every tile of a row has the same color
+    fn striped() -> Gradient {
+        Gradient::builder()
+            .top_left(Color::rgb(255, 0, 0))
+            .top_right(Color::rgb(255, 0, 0))
+            .bottom_left(Color::rgb(0, 0, 255))
+            .bottom_right(Color::rgb(0, 0, 255))
+            .build()
+    }
+
+    #[test]
+    fn solution_skips_interchangeable_tiles() {
+        let settings =
+            PuzzleSettings::custom(4, 3, striped(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        // tile 1 ends up in cell 2 of its own row, which looks solved
+        assert!(puzzle.make_move(Move::Swap(1, 5)) && puzzle.make_move(Move::Swap(2, 5)));
+        assert_eq!(puzzle.solution(), [Move::Swap(1, 5)]);
+
+        // so the hint never touches the tile the assist locked
+        puzzle.set_auto_lock(true);
+        assert_eq!(puzzle.get(2).lock, Some(Lock::Earned));
+        let hint = puzzle.use_hint(Some(HINTS_PER_LEVEL)).unwrap();
+        assert!(puzzle.make_move(hint));
+        assert!(puzzle.is_solved());
+    }
+
+    #[test]
+    fn every_shuffle_attempt_starts_from_solved() {
+        // every arrangement of a single colored row looks solved, so the
+        // shuffle uses up its attempts and keeps the last one
+        let settings =
+            PuzzleSettings::custom(8, 1, striped(), LockingPattern::Corners, MoveKind::Swaps)
+                .unwrap();
+        let puzzle = Puzzle::shuffled(settings.clone());
+
+        let attempt = MAX_SHUFFLE_ATTEMPTS as u64 - 1;
+        let mut rng =
+            rand_pcg::Pcg64Mcg::seed_from_u64(settings.shuffle_seed.wrapping_add(attempt));
+        let sources = ShuffleStrategy::Random.arrange(&[1, 2, 3, 4, 5, 6], (8, 1), &mut rng);
+        let tiles = puzzle.iter().map(|cell| cell.solved_position);
+        assert!(tiles.eq(sources));
+    }
+
+    #[test]
+    fn shift_boards_follow_their_solution() {
+        for &moves in &[MoveKind::Shifts, MoveKind::ShiftsAroundLocks] {
+            let settings =
+                PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, moves).unwrap();
+            let mut puzzle = Puzzle::shuffled(settings);
+            assert!(!puzzle.is_solved());
+            assert_eq!(puzzle.par(), puzzle.solution().len());
+
+            // locked corners block the outer lines unless shifts go around them
+            let outer = puzzle.make_move(Move::Shift {
+                line: Line::Row(0),
+                forwards: true,
+            });
+            assert_eq!(outer, moves == MoveKind::ShiftsAroundLocks);
+            assert!(!puzzle.make_move(Move::Swap(6, 7)));
+            if outer {
+                assert!(puzzle.undo() && puzzle.redo());
+                assert_eq!(puzzle.get(0).solved_position, 0);
+            }
+
+            while let Some(hint) = puzzle.solution().into_iter().next() {
+                assert!(puzzle.make_move(hint));
+            }
+            assert!(puzzle.is_solved());
+            assert!(puzzle.stats.moves <= puzzle.par() + 4);
+        }
+    }
+
+    #[test]
+    fn adjacent_swap_boards_are_solved_optimally() {
+        let moves = MoveKind::AdjacentSwaps;
+        let settings = PuzzleSettings::custom(5, 7, rainbow(), LockingPattern::Corners, moves);
+        let mut settings = settings.unwrap();
+        settings.shuffle_seed = 1;
+        let mut puzzle = Puzzle::shuffled(settings);
+        assert!(!puzzle.is_solved());
+        assert!(!puzzle.make_move(Move::Swap(6, 8)));
+        assert!(!puzzle.make_move(Move::Swap(9, 10)));
+
+        // the search finds a shorter way than undoing the scramble
+        let tiles = puzzle.iter().map(|cell| cell.solved_position);
+        let locked = puzzle.iter().map(PuzzleCell::is_locked);
+        let found =
+            adjacent_solver::solve(&tiles.collect::<Vec<_>>(), 5, &locked.collect::<Vec<_>>());
+        let par = puzzle.par();
+        assert_eq!(found.map(|solution| solution.len()), Some(par));
+        assert!(par < puzzle.scramble.len());
+
+        while let Some(hint) = puzzle.solution().into_iter().next() {
+            assert!(puzzle.make_move(hint));
+        }
+        assert!(puzzle.is_solved());
+        assert_eq!(puzzle.stats.moves, par);
+
+        // boards the search gives up on still get a solution that works
+        let settings = PuzzleSettings::custom(12, 12, rainbow(), LockingPattern::Corners, moves);
+        let mut puzzle = Puzzle::shuffled(settings.unwrap());
+        assert!(puzzle.par() > 0 && puzzle.par() <= puzzle.scramble.len());
+        for mv in puzzle.solution() {
+            assert!(puzzle.make_move(mv));
+        }
+        assert!(puzzle.is_solved());
+    }
+
+    #[test]
+    fn solutions_are_kept_until_a_tile_moves() {
+        let moves = MoveKind::AdjacentSwaps;
+        let settings = PuzzleSettings::custom(5, 7, rainbow(), LockingPattern::Corners, moves);
+        let mut settings = settings.unwrap();
+        settings.shuffle_seed = 1;
+        let mut puzzle = Puzzle::shuffled(settings);
+        let solution = puzzle.solution();
+        assert_eq!(puzzle.solution.borrow().as_ref(), Some(&solution));
+
+        let first = solution[0].clone();
+        assert!(puzzle.make_move(first.clone()));
+        assert!(puzzle.solution.borrow().is_none());
+        assert_eq!(puzzle.solution().len(), solution.len() - 1);
+        assert!(puzzle.undo());
+        assert!(puzzle.solution.borrow().is_none());
+        assert_eq!(puzzle.solution(), solution);
+        assert!(puzzle.redo());
+        assert!(puzzle.solution.borrow().is_none());
+    }
+
+    #[test]
+    fn undoing_a_cycle_needs_its_moves_left() {
+        let settings =
+            PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        assert!(puzzle.make_move(Move::rotation(vec![1, 2, 3])));
+        assert_eq!(puzzle.stats.moves, 2);
+
+        // one move left isn't enough to take back a cycle of three
+        let limit = Some(3);
+        assert_eq!(puzzle.moves_remaining(limit), Some(1));
+        let cycle = puzzle.next_undo().unwrap();
+        assert!(!puzzle.can_afford(cycle, limit));
+        assert!(puzzle.can_afford(cycle, Some(4)));
+        assert!(puzzle.can_afford(cycle, None));
+
+        assert!(!puzzle.is_out_of_moves(limit));
+
+        // a board that went past its limit is still out of moves
+        puzzle.stats.moves = 4;
+        assert!(puzzle.is_out_of_moves(limit));
+        assert!(!puzzle.is_out_of_moves(None));
+        puzzle.stats.moves = 2;
+
+        assert!(puzzle.undo());
+        let cycle = puzzle.next_redo().unwrap();
+        assert!(!puzzle.can_afford(cycle, Some(5)));
+        assert!(puzzle.can_afford(cycle, Some(6)));
+    }
+
+    #[test]
+    fn cycles_count_as_their_swaps() {
+        let settings =
+            PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        assert!(!puzzle.make_move(Move::Cycle(vec![1, 2, 1])));
+        assert!(!puzzle.make_move(Move::Cycle(vec![0, 1, 2])));
+
+        assert!(puzzle.make_move(Move::rotation(vec![1, 2, 3])));
+        assert_eq!(puzzle.stats.moves, 2);
+        let tiles = [1, 2, 3]
+            .iter()
+            .map(|&cell| puzzle.get(cell).solved_position);
+        assert!(tiles.eq(vec![3, 1, 2]));
+        assert_eq!(puzzle.solution().len(), 2);
+
+        // undoing takes back the whole cycle
+        assert!(puzzle.undo());
+        assert!(puzzle.is_solved());
+        assert_eq!(puzzle.stats.moves, 4);
+        assert!(puzzle.redo() && puzzle.next_redo().is_none());
+        assert_eq!(puzzle.get(2).solved_position, 1);
+
+        let moves = MoveKind::AdjacentSwaps;
+        let settings = PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, moves);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        assert!(!puzzle.make_move(Move::rotation(vec![1, 2, 3])));
+    }
+
+    #[test]
+    fn auto_lock_locks_placed_tiles() {
+        let settings =
+            PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        assert!(puzzle.make_move(Move::Swap(1, 2)) && puzzle.make_move(Move::Swap(6, 7)));
+        puzzle.set_auto_lock(true);
+        assert_eq!(puzzle.get(3).lock, Some(Lock::Earned));
+        assert_eq!(puzzle.get(0).lock, Some(Lock::Anchor));
+        assert!(!puzzle.get(1).is_locked());
+        assert!(!puzzle.make_move(Move::Swap(1, 3)));
+        assert_eq!(puzzle.correctly_placed(), 12);
+        // tiles that were already in place don't count as helped
+        assert!(!puzzle.stats.auto_locked);
+
+        assert!(puzzle.make_move(Move::Swap(1, 2)));
+        assert!(puzzle.get(1).is_locked() && puzzle.get(2).is_locked());
+        assert!(puzzle.stats.auto_locked);
+
+        // undoing releases the tiles it moves out of place
+        assert!(puzzle.undo());
+        assert!(!puzzle.get(1).is_locked() && !puzzle.get(2).is_locked());
+        puzzle.set_auto_lock(false);
+        assert!(puzzle.iter().all(|cell| cell.lock != Some(Lock::Earned)));
+        assert!(puzzle.stats.auto_locked);
+    }
+
+    #[test]
+    fn toggling_auto_lock_without_moving_isnt_counted() {
+        let settings =
+            PuzzleSettings::custom(5, 4, rainbow(), LockingPattern::Corners, MoveKind::Swaps);
+        let mut puzzle = Puzzle::unshuffled(settings.unwrap());
+        assert!(puzzle.make_move(Move::Swap(1, 2)));
+        puzzle.set_auto_lock(true);
+        assert!(puzzle.get(3).is_locked());
+        puzzle.set_auto_lock(false);
+        puzzle.set_auto_lock(true);
+        assert!(!puzzle.stats.auto_locked);
+
+        // a move that the assist locks tiles after does
+        assert!(puzzle.make_move(Move::Swap(1, 2)));
+        assert!(puzzle.is_solved() && puzzle.stats.auto_locked);
+
+        let mut puzzle = puzzle.restarted();
+        assert!(!puzzle.stats.auto_locked);
+        puzzle.set_auto_lock(false);
+        assert!(puzzle.make_move(Move::Swap(1, 2)) && puzzle.make_move(Move::Swap(1, 2)));
+        assert!(puzzle.is_solved() && !puzzle.stats.auto_locked);
+        // turning the assist on at the end still counts as finishing with it
+        puzzle.set_auto_lock(true);
+        assert!(puzzle.undo() && puzzle.redo());
+        assert!(puzzle.stats.auto_locked);
+    }
+
+    #[test]
+    fn restarting_keeps_used_hints_and_peeks() {
+        let settings = PuzzleSettings::from_difficulty(standard(), 5, 0, WIN_SIZE);
+        let mut puzzle = Puzzle::shuffled(settings);
+        let hint = puzzle.use_hint(Some(HINTS_PER_LEVEL)).unwrap();
+        assert!(puzzle.make_move(hint));
+        assert!(puzzle.use_peek(Some(PEEKS_PER_LEVEL)));
+
+        let restarted = puzzle.restarted();
+        assert_eq!(restarted.stats.moves, 0);
+        assert_eq!(restarted.stats.hints_used, 1);
+        assert_eq!(restarted.stats.peeks_used, 1);
+        assert!(restarted
+            .iter()
+            .eq(Puzzle::shuffled(puzzle.settings).iter()));
+    }
+
+    #[test]
+    fn hints_are_limited() {
+        let settings = PuzzleSettings::from_difficulty(standard(), 5, 0, WIN_SIZE);
+        let mut puzzle = Puzzle::shuffled(settings);
+        let limit = Some(HINTS_PER_LEVEL);
+        assert!((0..HINTS_PER_LEVEL).all(|_| puzzle.use_hint(limit).is_some()));
+        assert_eq!(puzzle.hints_remaining(limit), Some(0));
+        assert_eq!(puzzle.use_hint(limit), None);
+
+        // boards that aren't scored have as many as they like
+        assert_eq!(puzzle.hints_remaining(None), None);
+        assert!(puzzle.use_hint(None).is_some());
+        assert_eq!(puzzle.stats.hints_used, HINTS_PER_LEVEL + 1);
+    }
+
+    #[test]
+    fn peeks_are_limited() {
+        let settings = PuzzleSettings::from_difficulty(standard(), 5, 0, WIN_SIZE);
+        let mut puzzle = Puzzle::shuffled(settings);
+        let before = puzzle.clone();
+        assert!((0..PEEKS_PER_LEVEL).all(|_| puzzle.use_peek(Some(PEEKS_PER_LEVEL))));
+        assert!(!puzzle.use_peek(Some(PEEKS_PER_LEVEL)));
+        assert!(puzzle.use_peek(None));
+        assert_eq!(puzzle.stats.peeks_used, PEEKS_PER_LEVEL + 1);
+        assert!(puzzle.iter().eq(before.iter()));
     }
-    PATTERNS[DISTRIBUTION.sample(&mut thread_rng())].0
 }
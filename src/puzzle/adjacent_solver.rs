@@ -0,0 +1,311 @@
+//! Solves boards where tiles only swap with their neighbours.
+//!
+//! Cycle decomposition doesn't work when swaps are limited to neighbours, so
+//! the fewest swaps are searched for with IDA*. A sideways swap moves two
+//! tiles one column each, so at least half the total column distance of the
+//! tiles from their solved positions is left to make in sideways swaps, and
+//! the same goes for rows and up-and-down swaps. Every swap also flips
+//! whether the tiles are an odd or even number of swaps from solved, like the
+//! parity of the inversions in the 15 puzzle, so the estimate is rounded up
+//! to match it. That lets each pass of the search raise its bound by two.
+
+use super::Move;
+use std::collections::HashMap;
+
+/// How many swaps within the bound the search may make before giving up. The
+/// budget is counted in swaps rather than time so that a board always gets
+/// the same par.
+const SEARCH_BUDGET: usize = 1_000_000;
+
+/// Returns the fewest swaps of neighbouring unlocked cells that put every
+/// tile in place, or None if they couldn't be found within the budget.
+/// `tiles` holds the solved position of the tile in each cell.
+pub fn solve(tiles: &[usize], width: usize, locked: &[bool]) -> Option<Vec<Move>> {
+    let mut search = Search {
+        tiles: tiles.to_vec(),
+        width,
+        pairs: neighbours(tiles.len(), width, locked),
+        path: Vec::new(),
+        tried: 0,
+        hash: 0,
+        seen: HashMap::new(),
+    };
+    search.hash = search
+        .tiles
+        .iter()
+        .enumerate()
+        .fold(0, |hash, (cell, &tile)| hash ^ placement_key(cell, tile));
+    let totals = search.totals();
+
+    let mut bound = search.estimate(&totals);
+    loop {
+        match search.search(0, totals, bound, None) {
+            Step::Found => break,
+            Step::Exceeded(next) => {
+                bound = next;
+                search.seen.clear();
+            }
+            Step::OutOfBudget => return None,
+        }
+    }
+    let pairs = &search.pairs;
+    Some(
+        search
+            .path
+            .iter()
+            .map(|&pair| Move::Swap(pairs[pair].0, pairs[pair].1))
+            .collect(),
+    )
+}
+
+/// Returns the swaps that take back the made swaps, leaving out the ones that
+/// cancel each other. Used when the search runs out of budget, so it only
+/// needs to work, not be short.
+pub fn undo_swaps<'a, I>(made: I) -> Vec<Move>
+where
+    I: IntoIterator<Item = &'a Move>,
+{
+    let mut kept: Vec<(usize, usize)> = Vec::new();
+    for mv in made {
+        let (a, b) = match *mv {
+            Move::Swap(a, b) => (a.min(b), a.max(b)),
+            Move::Shift { .. } | Move::Cycle(_) => continue,
+        };
+        // swaps of other cells can be moved past, so look back to the last
+        // swap that touches either cell
+        let last = kept
+            .iter()
+            .rposition(|&(c, d)| c == a || c == b || d == a || d == b);
+        match last {
+            Some(index) if kept[index] == (a, b) => {
+                kept.remove(index);
+            }
+            _ => kept.push((a, b)),
+        }
+    }
+    kept.iter().rev().map(|&(a, b)| Move::Swap(a, b)).collect()
+}
+
+/// Every pair of unlocked cells that are next to each other
+pub fn neighbours(cells: usize, width: usize, locked: &[bool]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for cell in 0..cells {
+        if locked[cell] {
+            continue;
+        }
+        if (cell + 1) % width != 0 && cell + 1 < cells && !locked[cell + 1] {
+            pairs.push((cell, cell + 1));
+        }
+        if cell + width < cells && !locked[cell + width] {
+            pairs.push((cell, cell + width));
+        }
+    }
+    pairs
+}
+
+enum Step {
+    Found,
+    /// No solution within the bound, holding the smallest bound that went
+    /// over it
+    Exceeded(usize),
+    OutOfBudget,
+}
+
+/// The measures of how far the board is from solved that the estimate is
+/// made from, kept up to date as swaps are made
+#[derive(Clone, Copy)]
+struct Totals {
+    /// Columns between the tiles and their solved positions
+    columns: usize,
+    /// Rows between the tiles and their solved positions
+    rows: usize,
+    /// Whether an odd number of swaps is left to make
+    odd: bool,
+}
+
+/// A random looking key for a tile being in a cell. The hash of a board is
+/// all of its keys xored together, so a swap only changes four of them.
+fn placement_key(cell: usize, tile: usize) -> u64 {
+    // splitmix64
+    let mut key = ((cell as u64) << 32 | tile as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    key = (key ^ (key >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    key = (key ^ (key >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    key ^ (key >> 31)
+}
+
+struct Search {
+    tiles: Vec<usize>,
+    width: usize,
+    pairs: Vec<(usize, usize)>,
+    /// The pairs swapped so far
+    path: Vec<usize>,
+    tried: usize,
+    /// The hash of the tiles as they are
+    hash: u64,
+    /// The fewest swaps each board has been reached with within the bound.
+    /// Swaps of separate cells can be made in either order, so most boards
+    /// are reached more than once.
+    seen: HashMap<u64, usize>,
+}
+
+impl Search {
+    fn coordinates(&self, cell: usize) -> (usize, usize) {
+        (cell % self.width, cell / self.width)
+    }
+
+    fn totals(&self) -> Totals {
+        let mut totals = Totals {
+            columns: 0,
+            rows: 0,
+            odd: false,
+        };
+        let mut visited = vec![false; self.tiles.len()];
+        for (cell, &tile) in self.tiles.iter().enumerate() {
+            let ((x, y), (tile_x, tile_y)) = (self.coordinates(cell), self.coordinates(tile));
+            totals.columns += x.max(tile_x) - x.min(tile_x);
+            totals.rows += y.max(tile_y) - y.min(tile_y);
+
+            // a cycle of tiles takes one swap less than its length
+            let mut next = cell;
+            while !std::mem::replace(&mut visited[next], true) {
+                next = self.tiles[next];
+                totals.odd ^= next != cell;
+            }
+        }
+        totals
+    }
+
+    fn estimate(&self, totals: &Totals) -> usize {
+        let swaps = totals.columns.div_ceil(2) + totals.rows.div_ceil(2);
+        swaps + usize::from((swaps % 2 == 1) != totals.odd)
+    }
+
+    /// How much swapping the pair changes the total distance of the tiles
+    /// from their solved positions, which is always -2, 0 or 2
+    fn change(&self, (a, b): (usize, usize)) -> isize {
+        let (tile_a, tile_b) = (self.tiles[a], self.tiles[b]);
+        self.distance(b, tile_a) + self.distance(a, tile_b)
+            - self.distance(a, tile_a)
+            - self.distance(b, tile_b)
+    }
+
+    fn distance(&self, cell: usize, tile: usize) -> isize {
+        let ((x, y), (tile_x, tile_y)) = (self.coordinates(cell), self.coordinates(tile));
+        (x.max(tile_x) - x.min(tile_x) + y.max(tile_y) - y.min(tile_y)) as isize
+    }
+
+    /// What swapping the pair changes the hash of the board by
+    fn swap_key(&self, (a, b): (usize, usize)) -> u64 {
+        let (tile_a, tile_b) = (self.tiles[a], self.tiles[b]);
+        placement_key(a, tile_a)
+            ^ placement_key(b, tile_b)
+            ^ placement_key(a, tile_b)
+            ^ placement_key(b, tile_a)
+    }
+
+    /// The totals once the pair is swapped, worked out before swapping it
+    fn after(&self, totals: Totals, (a, b): (usize, usize)) -> Totals {
+        let change = (totals.columns + totals.rows) as isize + self.change((a, b));
+        let (columns, rows) = if a / self.width == b / self.width {
+            (change as usize - totals.rows, totals.rows)
+        } else {
+            (totals.columns, change as usize - totals.columns)
+        };
+        Totals {
+            columns,
+            rows,
+            odd: !totals.odd,
+        }
+    }
+
+    fn search(&mut self, swaps: usize, totals: Totals, bound: usize, last: Option<usize>) -> Step {
+        let estimate = swaps + self.estimate(&totals);
+        if estimate > bound {
+            return Step::Exceeded(estimate);
+        }
+        if totals.columns + totals.rows == 0 {
+            return Step::Found;
+        }
+        // a board that was already reached in as few swaps has been searched
+        match self.seen.get(&self.hash) {
+            Some(&fewest) if fewest <= swaps => return Step::Exceeded(usize::MAX),
+            _ => {
+                self.seen.insert(self.hash, swaps);
+            }
+        }
+
+        // try the swaps that bring both tiles closer first, going over the
+        // pairs once for each change rather than sorting them
+        let mut next = usize::MAX;
+        for &wanted in &[-2, 0, 2] {
+            for pair in 0..self.pairs.len() {
+                if Some(pair) == last || self.change(self.pairs[pair]) != wanted {
+                    continue;
+                }
+                let (a, b) = self.pairs[pair];
+                let totals = self.after(totals, (a, b));
+                let estimate = swaps + 1 + self.estimate(&totals);
+                if estimate > bound {
+                    next = next.min(estimate);
+                    continue;
+                }
+                self.tried += 1;
+                if self.tried > SEARCH_BUDGET {
+                    return Step::OutOfBudget;
+                }
+                let hash = self.hash;
+                self.hash ^= self.swap_key((a, b));
+                self.tiles.swap(a, b);
+                self.path.push(pair);
+                match self.search(swaps + 1, totals, bound, Some(pair)) {
+                    Step::Found => return Step::Found,
+                    Step::Exceeded(estimate) => next = next.min(estimate),
+                    Step::OutOfBudget => return Step::OutOfBudget,
+                }
+                self.path.pop();
+                self.tiles.swap(a, b);
+                self.hash = hash;
+            }
+        }
+        Step::Exceeded(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_fewest_swaps() {
+        // 0 1 2      2 1 0
+        // 3 4 5  ->  3 4 5
+        let tiles = [2, 1, 0, 3, 4, 5];
+        let solution = solve(&tiles, 3, &[false; 6]).unwrap();
+        assert_eq!(solution.len(), 3);
+
+        // a lock in the middle of the top row forces a detour
+        let locked = [false, true, false, false, false, false];
+        let solution = solve(&tiles, 3, &locked).unwrap();
+        assert_eq!(solution.len(), 7);
+        let mut tiles = tiles;
+        for mv in solution {
+            if let Move::Swap(a, b) = mv {
+                assert!(!locked[a] && !locked[b]);
+                tiles.swap(a, b);
+            }
+        }
+        assert_eq!(tiles, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn undoes_swaps() {
+        let made = [
+            Move::Swap(0, 1),
+            Move::Swap(2, 3),
+            Move::Swap(1, 0),
+            Move::Swap(3, 4),
+        ];
+        let expected = [Move::Swap(3, 4), Move::Swap(2, 3)];
+        assert_eq!(undo_swaps(&made), expected);
+    }
+}
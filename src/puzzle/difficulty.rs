@@ -0,0 +1,175 @@
+//! Estimates how hard a puzzle is to solve from the things that make it hard:
+//! how many tiles there are, how close the colors of neighbouring tiles are,
+//! how much help the locks give and how many swaps it takes to solve.
+
+use super::{LockSupport, Puzzle};
+use std::fmt;
+
+/// How much each factor contributes to the total, adding up to 100
+const SIZE_WEIGHT: f64 = 20.0;
+const MIN_CONTRAST_WEIGHT: f64 = 20.0;
+const MEAN_CONTRAST_WEIGHT: f64 = 25.0;
+const LOCKS_WEIGHT: f64 = 20.0;
+const SWAPS_WEIGHT: f64 = 15.0;
+
+/// The value of each measurement at which its factor is half way to its
+/// hardest score
+const SIZE_MIDPOINT: f64 = 150.0;
+const MIN_CONTRAST_MIDPOINT: f64 = 2.0;
+const MEAN_CONTRAST_MIDPOINT: f64 = 6.0;
+const SWAPS_MIDPOINT: f64 = 60.0;
+
+/// How far from its target the estimate of a generated puzzle may be
+pub const TOLERANCE: f64 = 8.0;
+
+/// Each factor is scored from 0 for the easiest boards towards 1 for the
+/// hardest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyEstimate {
+    /// From the number of tiles on the board
+    pub size: f64,
+    /// From the smallest color difference (in delta E) between neighbours
+    pub min_contrast: f64,
+    /// From the average color difference between neighbours
+    pub mean_contrast: f64,
+    /// From the lock support of the locking pattern, hidden locks give no
+    /// support at all
+    pub locks: f64,
+    /// From the fewest swaps that solve the board
+    pub swaps: f64,
+}
+
+impl DifficultyEstimate {
+    pub fn measure(puzzle: &Puzzle) -> Self {
+        let settings = &puzzle.settings;
+        let (width, height) = (settings.width, settings.height);
+        let lock_mask = settings.lock_mask();
+
+        // compare the solved colors of every pair of neighbours that the
+        // player has to place at least one of
+        let mut contrasts = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let color = settings.get_cell_color(x, y);
+                for &(nx, ny) in &[(x + 1, y), (x, y + 1)] {
+                    if nx < width && ny < height && !(lock_mask.get(x, y) && lock_mask.get(nx, ny))
+                    {
+                        contrasts.push(color.distance(settings.get_cell_color(nx, ny)));
+                    }
+                }
+            }
+        }
+        let min_contrast = contrasts.iter().cloned().fold(f64::INFINITY, f64::min);
+        let mean_contrast = contrasts.iter().sum::<f64>() / contrasts.len().max(1) as f64;
+
+        let cells = (width * height) as f64;
+        let swaps = puzzle.par() as f64;
+        Self {
+            size: cells / (cells + SIZE_MIDPOINT),
+            min_contrast: falloff(min_contrast, MIN_CONTRAST_MIDPOINT),
+            mean_contrast: falloff(mean_contrast, MEAN_CONTRAST_MIDPOINT),
+            locks: if settings.hidden_locks {
+                1.0
+            } else {
+                LockSupport::measure(&lock_mask).difficulty()
+            },
+            swaps: swaps / (swaps + SWAPS_MIDPOINT),
+        }
+    }
+
+    /// The contribution of each factor to the total
+    pub fn breakdown(&self) -> [(&'static str, f64); 5] {
+        [
+            ("size", self.size * SIZE_WEIGHT),
+            ("min contrast", self.min_contrast * MIN_CONTRAST_WEIGHT),
+            ("mean contrast", self.mean_contrast * MEAN_CONTRAST_WEIGHT),
+            ("locks", self.locks * LOCKS_WEIGHT),
+            ("swaps", self.swaps * SWAPS_WEIGHT),
+        ]
+    }
+
+    /// The estimated difficulty, from 0 towards 100
+    pub fn total(&self) -> f64 {
+        self.breakdown().iter().map(|(_, score)| score).sum()
+    }
+}
+
+impl fmt::Display for DifficultyEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} (", self.total())?;
+        for (i, (name, score)) in self.breakdown().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:.1}", name, score)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Scores a measurement where smaller values are harder, infinity scores 0
+fn falloff(value: f64, midpoint: f64) -> f64 {
+    if value.is_finite() {
+        midpoint / (value.max(0.0) + midpoint)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient::{Color, Gradient};
+    use crate::puzzle::{LockingPattern, MoveKind, PuzzleSettings};
+
+    fn estimate(width: usize, height: usize, corners: [Color; 4]) -> DifficultyEstimate {
+        DifficultyEstimate::measure(&puzzle(width, height, corners))
+    }
+
+    fn puzzle(width: usize, height: usize, corners: [Color; 4]) -> Puzzle {
+        let gradient = Gradient::builder()
+            .top_left(corners[0])
+            .top_right(corners[1])
+            .bottom_left(corners[2])
+            .bottom_right(corners[3])
+            .build();
+        let pattern = LockingPattern::Corners;
+        let settings =
+            PuzzleSettings::custom(width, height, gradient, pattern, MoveKind::Swaps).unwrap();
+        Puzzle::shuffled(settings)
+    }
+
+    #[test]
+    fn harder_boards_score_higher() {
+        let vivid = [
+            Color::rgb(255, 0, 0),
+            Color::rgb(255, 255, 0),
+            Color::rgb(0, 0, 255),
+            Color::rgb(0, 255, 0),
+        ];
+        let muted = [
+            Color::rgb(120, 100, 100),
+            Color::rgb(120, 120, 100),
+            Color::rgb(100, 100, 120),
+            Color::rgb(100, 120, 100),
+        ];
+
+        let small = estimate(5, 5, vivid);
+        let big = estimate(15, 15, vivid);
+        let big_muted = estimate(15, 15, muted);
+        assert!(small.total() < big.total());
+        assert!(big.total() < big_muted.total());
+        assert!(big.size > small.size && big.swaps > small.swaps);
+        assert!(big_muted.mean_contrast > big.mean_contrast);
+        assert_eq!(big.locks, big_muted.locks);
+
+        let total = big.breakdown().iter().map(|(_, score)| score).sum::<f64>();
+        assert_eq!(big.total(), total);
+
+        let mut hidden = puzzle(15, 15, vivid);
+        hidden.set_hidden_locks(true);
+        let hidden = DifficultyEstimate::measure(&hidden);
+        assert!(hidden.total() > big.total());
+        assert_eq!(hidden.locks, 1.0);
+    }
+}
@@ -0,0 +1,139 @@
+//! Puzzles as they were saved before saves had a version. Those saves only
+//! hold the board, so the migrated puzzle starts out with no moves made.
+
+use super::{
+    Lock, LockingPattern, MoveKind, Puzzle, PuzzleCell, PuzzleSettings, ShuffleStrategy,
+    DEFAULT_COLOR_TOLERANCE,
+};
+use crate::gradient::{Color, Gradient};
+use crate::grid::Grid;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct LegacyPuzzle {
+    settings: LegacySettings,
+    grid: Grid<LegacyCell>,
+}
+
+/// The locking patterns of the time are the first of today's, in the same
+/// order, so they decode as they are
+#[derive(Deserialize)]
+struct LegacySettings {
+    difficulty: usize,
+    width: usize,
+    height: usize,
+    gradient: Gradient,
+    locking_pattern: LockingPattern,
+    shuffle_seed: u64,
+}
+
+#[derive(Deserialize)]
+struct LegacyCell {
+    solved_position: usize,
+    is_locked: bool,
+    color: Color,
+}
+
+/// Decodes an unversioned save into a puzzle of the same level, keeping the
+/// tiles where the player left them
+pub fn migrate(bin: &[u8]) -> Option<Puzzle> {
+    let LegacyPuzzle { settings, grid } = bincode::deserialize(bin).ok()?;
+    let LegacySettings {
+        difficulty,
+        width,
+        height,
+        gradient,
+        locking_pattern,
+        shuffle_seed,
+    } = settings;
+    if grid.dims() != (width, height) {
+        return None;
+    }
+
+    let settings = PuzzleSettings {
+        difficulty,
+        width,
+        height,
+        gradient,
+        locking_pattern,
+        shuffle: ShuffleStrategy::Random,
+        shuffle_seed,
+        color_tolerance: DEFAULT_COLOR_TOLERANCE,
+        hidden_locks: false,
+        moves: MoveKind::Swaps,
+    };
+    let mut puzzle = Puzzle::unshuffled(settings);
+    puzzle.grid = Grid::from_closure(width, height, |x, y| {
+        let cell = grid.get(y * width + x);
+        PuzzleCell {
+            solved_position: cell.solved_position,
+            lock: if cell.is_locked {
+                Some(Lock::Anchor)
+            } else {
+                None
+            },
+            color: cell.color,
+        }
+    });
+    puzzle.par = puzzle.solution().len();
+    Some(puzzle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{cell_position, SAVE_VERSION};
+    use super::*;
+    use crate::savegame;
+
+    #[test]
+    fn migrates_unversioned_saves() {
+        let gradient = Gradient::builder()
+            .top_left(Color::rgb(255, 0, 0))
+            .top_right(Color::rgb(255, 255, 0))
+            .bottom_left(Color::rgb(0, 0, 255))
+            .bottom_right(Color::rgb(0, 255, 0))
+            .build();
+        // a 3x2 board with locked corners and its two middle tiles swapped,
+        // laid out the way the first version saved it
+        let (width, height) = (3usize, 2usize);
+        let cells = [0usize, 4, 2, 3, 1, 5]
+            .iter()
+            .map(|&solved| {
+                let position = cell_position(solved % width, solved / width, width, height);
+                (
+                    solved,
+                    solved != 1 && solved != 4,
+                    gradient.color_at(position),
+                )
+            })
+            .collect::<Vec<_>>();
+        let saved = (
+            (
+                7usize,
+                width,
+                height,
+                gradient,
+                LockingPattern::Corners,
+                1234u64,
+            ),
+            (width, height, cells),
+        );
+        let bin = bincode::serialize(&saved).unwrap();
+
+        let puzzle = savegame::decode_versioned(&bin, SAVE_VERSION, migrate).unwrap();
+        assert_eq!(puzzle.validate(), Ok(()));
+        assert_eq!(puzzle.settings.difficulty(), 7);
+        assert_eq!(puzzle.get(0).lock, Some(Lock::Anchor));
+        assert_eq!(puzzle.get(1).solved_position, 4);
+        assert!(!puzzle.get(1).is_locked());
+        assert_eq!((puzzle.par(), puzzle.stats.moves), (1, 0));
+
+        // once saved again it loads as the current version
+        let bin = savegame::encode_versioned(SAVE_VERSION, &puzzle)
+            .ok()
+            .unwrap();
+        let resaved: Puzzle = savegame::decode_versioned(&bin, SAVE_VERSION, |_| None).unwrap();
+        assert!(resaved.iter().eq(puzzle.iter()));
+        assert!(savegame::decode_versioned(&bin, SAVE_VERSION + 1, migrate).is_none());
+    }
+}
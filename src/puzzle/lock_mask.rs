@@ -0,0 +1,300 @@
+//! Hand made lock layouts.
+//!
+//! Masks are written as ascii art with a `#` for every locked cell and a `.`
+//! for every unlocked one, one line per row:
+//!
+//! ```text
+//! #...#
+//! ..#..
+//! #...#
+//! ```
+//!
+//! In human readable formats (like json) masks are serialised as a list of
+//! these rows, otherwise they are packed one bit per cell.
+//!
+//! A mask can be applied to a board of any size, see `LockMask::scaled`.
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+const LOCKED: char = '#';
+const UNLOCKED: char = '.';
+
+/// A hand made set of locked cells, packed one bit per cell in row order
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockMask {
+    width: usize,
+    height: usize,
+    bits: Vec<u8>,
+}
+
+impl LockMask {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            bits: vec![0; (width * height).div_ceil(8)],
+        }
+    }
+
+    pub fn from_bytes(width: usize, height: usize, bits: Vec<u8>) -> Option<Self> {
+        if bits.len() == (width * height).div_ceil(8) {
+            Some(Self {
+                width,
+                height,
+                bits,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Copies the locked cells of any pattern into a mask
+    pub fn from_closure<F>(width: usize, height: usize, is_locked: F) -> Self
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        let mut mask = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                mask.set(x, y, is_locked(x, y));
+            }
+        }
+        mask
+    }
+
+    pub fn dims(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits
+    }
+
+    /// Cells outside of the mask are never locked
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let index = y * self.width + x;
+        self.bits[index / 8] & (1 << (index % 8)) != 0
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, locked: bool) {
+        assert!(
+            x < self.width && y < self.height,
+            "Cell is outside of the lock mask"
+        );
+        let index = y * self.width + x;
+        if locked {
+            self.bits[index / 8] |= 1 << (index % 8);
+        } else {
+            self.bits[index / 8] &= !(1 << (index % 8));
+        }
+    }
+
+    pub fn toggle(&mut self, x: usize, y: usize) {
+        self.set(x, y, !self.get(x, y));
+    }
+
+    /// Stretches the mask over a board of a different size.
+    ///
+    /// The first and last rows and columns of the board take their value
+    /// from the first and last rows and columns of the mask, and the inner
+    /// cells of the board from the nearest inner cell of the mask. That way
+    /// locks on the corners and borders of the mask stay on the corners and
+    /// borders of the board, and a mask that is a single row or column wide
+    /// is repeated along that axis.
+    pub fn scaled(&self, width: usize, height: usize) -> LockMask {
+        if (width, height) == self.dims() {
+            return self.clone();
+        }
+
+        fn nearest(v: usize, from: usize, to: usize) -> usize {
+            if from <= 1 {
+                (to - 1) / 2
+            } else {
+                (v as f64 * (to - 1) as f64 / (from - 1) as f64).round() as usize
+            }
+        }
+
+        fn scale(v: usize, from: usize, to: usize) -> usize {
+            if to <= 1 || v == 0 {
+                0
+            } else if v == from - 1 {
+                to - 1
+            } else if to == 2 {
+                nearest(v, from, to)
+            } else {
+                1 + nearest(v - 1, from - 2, to - 2)
+            }
+        }
+
+        Self::from_closure(width, height, |x, y| {
+            self.get(scale(x, width, self.width), scale(y, height, self.height))
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseLockMaskError {
+    Empty,
+    UnevenRow(usize),
+    InvalidCell(char),
+}
+
+impl fmt::Display for ParseLockMaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "lock mask has no cells"),
+            Self::UnevenRow(row) => write!(f, "row {} of the lock mask has the wrong length", row),
+            Self::InvalidCell(cell) => write!(
+                f,
+                "lock mask cells must be '{}' or '{}', found {:?}",
+                LOCKED, UNLOCKED, cell
+            ),
+        }
+    }
+}
+
+/// Parses the ascii art format, surrounding whitespace and blank lines are ignored
+impl FromStr for LockMask {
+    type Err = ParseLockMaskError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let rows = text
+            .lines()
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>();
+
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err(ParseLockMaskError::Empty);
+        }
+
+        let mut mask = Self::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err(ParseLockMaskError::UnevenRow(y));
+            }
+            for (x, cell) in row.chars().enumerate() {
+                match cell {
+                    LOCKED => mask.set(x, y, true),
+                    UNLOCKED => {}
+                    _ => return Err(ParseLockMaskError::InvalidCell(cell)),
+                }
+            }
+        }
+        Ok(mask)
+    }
+}
+
+impl fmt::Display for LockMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.rows().iter().enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+impl LockMask {
+    fn rows(&self) -> Vec<String> {
+        (0..self.height)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| if self.get(x, y) { LOCKED } else { UNLOCKED })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Serialize for LockMask {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            self.rows().serialize(serializer)
+        } else {
+            (self.width, self.height, &self.bits).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LockMask {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let rows = Vec::<String>::deserialize(deserializer)?;
+            rows.join("\n").parse().map_err(D::Error::custom)
+        } else {
+            let (width, height, bits) = <(usize, usize, Vec<u8>)>::deserialize(deserializer)?;
+            Self::from_bytes(width, height, bits)
+                .ok_or_else(|| D::Error::custom("lock mask bits don't match its size"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let mask = "
+            #..#
+            .##.
+        "
+        .parse::<LockMask>()
+        .unwrap();
+        assert_eq!(mask.dims(), (4, 2));
+        assert!(mask.get(0, 0) && mask.get(3, 0) && mask.get(1, 1) && mask.get(2, 1));
+        assert!(!mask.get(1, 0) && !mask.get(0, 1));
+        assert_eq!(mask.to_string(), "#..#\n.##.");
+        assert_eq!(mask.to_string().parse(), Ok(mask));
+
+        assert_eq!("".parse::<LockMask>(), Err(ParseLockMaskError::Empty));
+        assert_eq!(
+            "#.\n#".parse::<LockMask>(),
+            Err(ParseLockMaskError::UnevenRow(1))
+        );
+        assert_eq!(
+            "#x".parse::<LockMask>(),
+            Err(ParseLockMaskError::InvalidCell('x'))
+        );
+    }
+
+    #[test]
+    fn scaling_keeps_corners_and_borders() {
+        let corners = "#.#\n...\n#.#".parse::<LockMask>().unwrap();
+        let scaled = corners.scaled(7, 10);
+        for y in 0..10 {
+            for x in 0..7 {
+                let is_corner = (x == 0 || x == 6) && (y == 0 || y == 9);
+                assert_eq!(scaled.get(x, y), is_corner, "({}, {})", x, y);
+            }
+        }
+
+        let border = "###\n#.#\n###".parse::<LockMask>().unwrap();
+        assert_eq!(
+            border.scaled(5, 4).to_string(),
+            "#####\n#...#\n#...#\n#####"
+        );
+
+        let column = "#\n.".parse::<LockMask>().unwrap();
+        assert_eq!(column.scaled(3, 2).to_string(), "###\n...");
+
+        assert_eq!(border.scaled(3, 3), border);
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let mask = "#..\n.#.".parse::<LockMask>().unwrap();
+        let bytes = bincode::serialize(&mask).unwrap();
+        assert_eq!(bincode::deserialize::<LockMask>(&bytes).unwrap(), mask);
+    }
+}
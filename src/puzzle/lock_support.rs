@@ -0,0 +1,139 @@
+use super::LockMask;
+use std::collections::VecDeque;
+
+/// Average anchor distance at which the distance part of the score is half
+/// way to its maximum
+const ANCHOR_DISTANCE_MIDPOINT: f64 = 2.0;
+
+/// How much help the locks on a board give the player when working out
+/// where the other tiles go
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LockSupport {
+    /// Fraction of the cells that are locked
+    pub locked: f64,
+    /// Fraction of the cells in the biggest connected group of unlocked cells
+    pub largest_region: f64,
+    /// Average number of steps from an unlocked cell to its nearest lock,
+    /// infinite when nothing is locked
+    pub anchor_distance: f64,
+}
+
+impl LockSupport {
+    pub fn measure(mask: &LockMask) -> Self {
+        let (width, height) = mask.dims();
+        let cells = width * height;
+        let neighbours = |index: usize| {
+            let (x, y) = (index % width, index / width);
+            let mut neighbours = Vec::with_capacity(4);
+            if x > 0 {
+                neighbours.push(index - 1);
+            }
+            if x + 1 < width {
+                neighbours.push(index + 1);
+            }
+            if y > 0 {
+                neighbours.push(index - width);
+            }
+            if y + 1 < height {
+                neighbours.push(index + width);
+            }
+            neighbours
+        };
+        let is_locked = |index: usize| mask.get(index % width, index / width);
+
+        // breadth first search outwards from every lock at once
+        let mut distances = vec![None; cells];
+        let mut queue = (0..cells)
+            .filter(|&i| is_locked(i))
+            .collect::<VecDeque<_>>();
+        for &index in &queue {
+            distances[index] = Some(0);
+        }
+        let locked = queue.len();
+        while let Some(index) = queue.pop_front() {
+            let distance = distances[index].unwrap_or(0) + 1;
+            for next in neighbours(index) {
+                if distances[next].is_none() {
+                    distances[next] = Some(distance);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        // flood fill each group of unlocked cells
+        let mut visited = vec![false; cells];
+        let mut largest_region = 0;
+        for start in 0..cells {
+            if visited[start] || is_locked(start) {
+                continue;
+            }
+            visited[start] = true;
+            let mut stack = vec![start];
+            let mut size = 0;
+            while let Some(index) = stack.pop() {
+                size += 1;
+                for next in neighbours(index) {
+                    if !visited[next] && !is_locked(next) {
+                        visited[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            largest_region = largest_region.max(size);
+        }
+
+        let unlocked = cells - locked;
+        let anchor_distance = if locked == 0 {
+            f64::INFINITY
+        } else if unlocked == 0 {
+            0.0
+        } else {
+            let total = distances.iter().flatten().sum::<usize>();
+            total as f64 / unlocked as f64
+        };
+
+        Self {
+            locked: locked as f64 / cells.max(1) as f64,
+            largest_region: largest_region as f64 / cells.max(1) as f64,
+            anchor_distance,
+        }
+    }
+
+    /// Combines the measurements into a single score, from 0 for a board
+    /// that's entirely locked towards 1 for a board with barely any locks
+    pub fn difficulty(&self) -> f64 {
+        let distance = if self.anchor_distance.is_finite() {
+            self.anchor_distance / (self.anchor_distance + ANCHOR_DISTANCE_MIDPOINT)
+        } else {
+            1.0
+        };
+        0.3 * (1.0 - self.locked) + 0.3 * self.largest_region + 0.4 * distance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure() {
+        let mask = "
+            #....
+            .....
+            #####
+            ....#
+        "
+        .parse::<LockMask>()
+        .unwrap();
+        let support = LockSupport::measure(&mask);
+        assert_eq!(support.locked, 7.0 / 20.0);
+        assert_eq!(support.largest_region, 9.0 / 20.0);
+        // distances of the unlocked cells, row by row
+        let total = (1 + 2 + 2 + 2) + 5 + 4;
+        assert_eq!(support.anchor_distance, total as f64 / 13.0);
+
+        let open = LockSupport::measure(&LockMask::new(3, 3));
+        assert_eq!(open.difficulty(), 1.0);
+        assert!(support.difficulty() < open.difficulty());
+    }
+}
@@ -0,0 +1,430 @@
+use super::{DifficultyProfile, LockMask, LockSupport};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The newer patterns are split into regions of this many cells square and
+/// every region is guaranteed to have at least one locked anchor
+const ANCHOR_REGION_SIZE: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LockingPattern {
+    Corners,
+    Borders,
+    ReverseBorders,
+    CheckerboardA,
+    CheckerboardB,
+    HalfCheckerboardA,
+    HalfCheckerboardB,
+    ShortLinesA,
+    ShortLinesB,
+    LongLinesA,
+    LongLinesB,
+    Custom(LockMask),
+    /// Every third diagonal, running down to the right (A) or to the left (B)
+    DiagonalsA,
+    DiagonalsB,
+    /// Both of the board's diagonals
+    Cross,
+    /// The middle row and column
+    Plus,
+    /// Every other ring, going in from the border
+    Rings,
+    Spiral,
+    /// Randomly placed locks, `density` is the percentage of locked cells
+    Scatter {
+        density: u8,
+        seed: u64,
+    },
+    EveryNthRow(u8),
+    EveryNthColumn(u8),
+    /// A block a third of the board's size in the middle
+    CentreBlock,
+}
+
+impl LockingPattern {
+    /// All of the built-in patterns, patterns with parameters use defaults
+    pub const PRESETS: [LockingPattern; 21] = [
+        LockingPattern::Corners,
+        LockingPattern::Borders,
+        LockingPattern::ReverseBorders,
+        LockingPattern::CheckerboardA,
+        LockingPattern::CheckerboardB,
+        LockingPattern::HalfCheckerboardA,
+        LockingPattern::HalfCheckerboardB,
+        LockingPattern::ShortLinesA,
+        LockingPattern::ShortLinesB,
+        LockingPattern::LongLinesA,
+        LockingPattern::LongLinesB,
+        LockingPattern::DiagonalsA,
+        LockingPattern::DiagonalsB,
+        LockingPattern::Cross,
+        LockingPattern::Plus,
+        LockingPattern::Rings,
+        LockingPattern::Spiral,
+        LockingPattern::Scatter {
+            density: 20,
+            seed: 0,
+        },
+        LockingPattern::EveryNthRow(3),
+        LockingPattern::EveryNthColumn(3),
+        LockingPattern::CentreBlock,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        use LockingPattern::*;
+        match self {
+            Corners => "Corners",
+            Borders => "Borders",
+            ReverseBorders => "Inner Block",
+            CheckerboardA => "Checkerboard",
+            CheckerboardB => "Inverse Checkerboard",
+            HalfCheckerboardA => "Sparse Grid",
+            HalfCheckerboardB => "Inverse Sparse Grid",
+            ShortLinesA => "Short Lines",
+            ShortLinesB => "Inverse Short Lines",
+            LongLinesA => "Long Lines",
+            LongLinesB => "Inverse Long Lines",
+            Custom(_) => "Custom",
+            DiagonalsA => "Diagonals",
+            DiagonalsB => "Reverse Diagonals",
+            Cross => "Diagonal Cross",
+            Plus => "Cross",
+            Rings => "Rings",
+            Spiral => "Spiral",
+            Scatter { .. } => "Scatter",
+            EveryNthRow(_) => "Rows",
+            EveryNthColumn(_) => "Columns",
+            CentreBlock => "Centre Block",
+        }
+    }
+
+    /// The locked cells of the pattern on a board of the given size
+    pub fn mask(&self, width: usize, height: usize) -> LockMask {
+        use LockingPattern::*;
+
+        let cells = |is_locked: &dyn Fn(usize, usize) -> bool| {
+            LockMask::from_closure(width, height, is_locked)
+        };
+        let is_corner = |x, y| (x == 0 || x == (width - 1)) && (y == 0 || y == (height - 1));
+        let is_border = |x, y| x == 0 || x == (width - 1) || y == 0 || y == (height - 1);
+        let is_checkboard = |x: usize, y: usize| (x + y) % 2 == 0;
+        let is_shortlines = |x, y| (if width > height { x } else { y }) % 2 == 0;
+        let is_longlines = |x, y| (if width < height { x } else { y }) % 2 == 0;
+        let is_diagonal = |x: usize, y: usize| {
+            // within half a cell of the line from the top to the bottom corner
+            let a = (x * (height - 1)) as i64;
+            let b = (y * (width - 1)) as i64;
+            2 * (a - b).abs() <= (width.max(height) - 1) as i64
+        };
+        let is_middle = |v: usize, size: usize| v == (size - 1) / 2 || v == size / 2;
+
+        let mut mask = match self {
+            Corners => cells(&is_corner),
+            Borders => cells(&is_border),
+            ReverseBorders => cells(&|x, y| !is_border(x, y)),
+            CheckerboardA => cells(&is_checkboard),
+            CheckerboardB => cells(&|x, y| !is_checkboard(x, y)),
+            HalfCheckerboardA => cells(&|x, y| x % 2 == 0 && y % 2 == 0),
+            HalfCheckerboardB => cells(&|x, y| x % 2 != 0 && y % 2 != 0),
+            ShortLinesA => cells(&is_shortlines),
+            ShortLinesB => cells(&|x, y| !is_shortlines(x, y)),
+            LongLinesA => cells(&is_longlines),
+            LongLinesB => cells(&|x, y| !is_longlines(x, y)),
+            Custom(mask) => mask.scaled(width, height),
+            DiagonalsA => cells(&|x, y| (x + y).is_multiple_of(3)),
+            DiagonalsB => cells(&|x, y| (x + height - 1 - y).is_multiple_of(3)),
+            Cross => cells(&|x, y| is_diagonal(x, y) || is_diagonal(width - 1 - x, y)),
+            Plus => cells(&|x, y| is_middle(x, width) || is_middle(y, height)),
+            Rings => cells(&|x, y| {
+                x.min(y)
+                    .min(width - 1 - x)
+                    .min(height - 1 - y)
+                    .is_multiple_of(2)
+            }),
+            Spiral => spiral(width, height),
+            Scatter { density, seed } => cells(&|x, y| {
+                let hash = splitmix64(seed ^ (y * width + x) as u64);
+                hash % 100 < u64::from(*density)
+            }),
+            EveryNthRow(n) => cells(&|_, y| y.is_multiple_of(usize::from(*n).max(1))),
+            EveryNthColumn(n) => cells(&|x, _| x.is_multiple_of(usize::from(*n).max(1))),
+            CentreBlock => {
+                let in_middle = |v: usize, size: usize| {
+                    let block = size.div_ceil(3);
+                    let start = (size - block) / 2;
+                    v >= start && v < start + block
+                };
+                cells(&|x, y| in_middle(x, width) && in_middle(y, height))
+            }
+        };
+        if self.guarantees_anchors() {
+            add_anchors(&mut mask);
+        }
+        mask
+    }
+
+    /// The original patterns are left as they were so that their boards
+    /// (and share codes) stay the same, and custom masks are left as made
+    fn guarantees_anchors(&self) -> bool {
+        use LockingPattern::*;
+        match self {
+            Corners | Borders | ReverseBorders | CheckerboardA | CheckerboardB
+            | HalfCheckerboardA | HalfCheckerboardB | ShortLinesA | ShortLinesB | LongLinesA
+            | LongLinesB | Custom(_) => false,
+            DiagonalsA
+            | DiagonalsB
+            | Cross
+            | Plus
+            | Rings
+            | Spiral
+            | Scatter { .. }
+            | EveryNthRow(_)
+            | EveryNthColumn(_)
+            | CentreBlock => true,
+        }
+    }
+}
+
+/// Locks the middle cell of every region that has no locked cells
+fn add_anchors(mask: &mut LockMask) {
+    let (width, height) = mask.dims();
+    for region_y in (0..height).step_by(ANCHOR_REGION_SIZE) {
+        for region_x in (0..width).step_by(ANCHOR_REGION_SIZE) {
+            let xs = region_x..(region_x + ANCHOR_REGION_SIZE).min(width);
+            let ys = region_y..(region_y + ANCHOR_REGION_SIZE).min(height);
+
+            let has_anchor = ys.clone().any(|y| xs.clone().any(|x| mask.get(x, y)));
+            if !has_anchor {
+                mask.set((xs.start + xs.end) / 2, (ys.start + ys.end) / 2, true);
+            }
+        }
+    }
+}
+
+/// A square spiral wall winding in from the top left corner, with a one cell
+/// wide corridor between each lap
+fn spiral(width: usize, height: usize) -> LockMask {
+    const DIRECTIONS: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+    let mut mask = LockMask::new(width, height);
+    mask.set(0, 0, true);
+
+    let (mut x, mut y) = (0, 0);
+    for segment in 0.. {
+        // the first three segments run along the border, after that every
+        // segment is two cells shorter than the previous one along its axis
+        let base = if segment % 2 == 0 { width } else { height } as i64 - 1;
+        let length = base - 2 * ((segment.max(1) - 1) / 2);
+        if length <= 0 {
+            break;
+        }
+
+        let (dx, dy) = DIRECTIONS[segment as usize % 4];
+        for _ in 0..length {
+            x += dx;
+            y += dy;
+            mask.set(x as usize, y as usize, true);
+        }
+    }
+    mask
+}
+
+// source: http://xoshiro.di.unimi.it/splitmix64.c
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// How far the lock support of a generated pattern may stray from its target
+const SUPPORT_TOLERANCE: f64 = 0.1;
+
+/// How many patterns are tried when looking for one close to the target
+const CANDIDATES: usize = 8;
+
+/// Generates a random locking pattern for a board of the given size.
+///
+/// Candidates are drawn using the profile's weights, which move from the
+/// easier patterns to the harder ones as the difficulty rises, and the first
+/// one whose measured lock support is close to the target for the difficulty
+/// is used (or the closest one if none are). As the target only ever rises
+/// with difficulty consecutive levels don't swing between very easy and very
+/// hard locks.
+pub fn generate<R: Rng>(
+    profile: &DifficultyProfile,
+    difficulty: usize,
+    (width, height): (usize, usize),
+    rng: &mut R,
+) -> LockingPattern {
+    use LockingPattern::*;
+
+    let progress = profile.progress(difficulty);
+    let target = profile.target_support(difficulty);
+    let weights = &profile.pattern_weights;
+    let distribution = WeightedIndex::new(
+        weights
+            .iter()
+            .map(|weight| weight.easiest + (weight.hardest - weight.easiest) * progress),
+    )
+    .unwrap();
+
+    let mut closest: Option<(LockingPattern, f64)> = None;
+    for _ in 0..CANDIDATES {
+        // sparser scattered locks and rows/columns further apart are harder
+        let pattern = match weights[distribution.sample(rng)].pattern.clone() {
+            Scatter { .. } => {
+                let density = 30.0 - 20.0 * progress + rng.gen_range(-5.0, 5.0);
+                Scatter {
+                    density: density.round() as u8,
+                    seed: rng.gen(),
+                }
+            }
+            EveryNthRow(_) => EveryNthRow(rng.gen_range(2, 3 + (2.0 * progress) as u8)),
+            EveryNthColumn(_) => EveryNthColumn(rng.gen_range(2, 3 + (2.0 * progress) as u8)),
+            pattern => pattern,
+        };
+
+        let support = LockSupport::measure(&pattern.mask(width, height));
+        let error = (support.difficulty() - target).abs();
+        if error <= SUPPORT_TOLERANCE {
+            return pattern;
+        }
+        if closest.as_ref().is_none_or(|(_, closest)| error < *closest) {
+            closest = Some((pattern, error));
+        }
+    }
+    closest.unwrap().0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked_count(mask: &LockMask) -> usize {
+        let (width, height) = mask.dims();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| mask.get(x, y))
+            .count()
+    }
+
+    /// A board can be solved by reasoning about the gradient when it has at
+    /// least one anchor and two tiles that can be swapped
+    fn assert_solvable(pattern: &LockingPattern, width: usize, height: usize) {
+        let mask = pattern.mask(width, height);
+        let locked = locked_count(&mask);
+        assert!(
+            locked >= 1,
+            "{:?} {}x{} has no anchors",
+            pattern,
+            width,
+            height
+        );
+        assert!(
+            width * height - locked >= 2,
+            "{:?} {}x{} has less than two unlocked tiles",
+            pattern,
+            width,
+            height
+        );
+
+        if pattern.guarantees_anchors() {
+            for region_y in (0..height).step_by(ANCHOR_REGION_SIZE) {
+                for region_x in (0..width).step_by(ANCHOR_REGION_SIZE) {
+                    let has_anchor =
+                        (region_y..(region_y + ANCHOR_REGION_SIZE).min(height)).any(|y| {
+                            (region_x..(region_x + ANCHOR_REGION_SIZE).min(width))
+                                .any(|x| mask.get(x, y))
+                        });
+                    assert!(
+                        has_anchor,
+                        "{:?} {}x{} has no anchor in region ({}, {})",
+                        pattern, width, height, region_x, region_y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_pattern_is_solvable() {
+        let mut patterns = LockingPattern::PRESETS.to_vec();
+        for density in &[0, 10, 30] {
+            for seed in 0..10 {
+                patterns.push(LockingPattern::Scatter {
+                    density: *density,
+                    seed,
+                });
+            }
+        }
+        for n in 2..5 {
+            patterns.push(LockingPattern::EveryNthRow(n));
+            patterns.push(LockingPattern::EveryNthColumn(n));
+        }
+
+        for pattern in &patterns {
+            for width in 5..=25 {
+                for height in 5..=25 {
+                    assert_solvable(pattern, width, height);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_patterns_are_solvable() {
+        let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(0);
+        for profile in DifficultyProfile::all() {
+            for difficulty in 1..500 {
+                let pattern = generate(profile, difficulty, (5, 9), &mut rng);
+                assert_solvable(&pattern, 5, 9);
+                let pattern = generate(profile, difficulty, (12, 20), &mut rng);
+                assert_solvable(&pattern, 12, 20);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_lock_support_rises_with_difficulty() {
+        let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(0);
+        for profile in DifficultyProfile::all() {
+            let mut previous = 0.0;
+            for difficulty in &[1, 25, 100, 500] {
+                let mean = (0..200)
+                    .map(|_| {
+                        let pattern = generate(profile, *difficulty, (16, 9), &mut rng);
+                        LockSupport::measure(&pattern.mask(16, 9)).difficulty()
+                    })
+                    .sum::<f64>()
+                    / 200.0;
+                let target = profile.target_support(*difficulty);
+                assert!(
+                    mean >= previous,
+                    "{} {} got easier",
+                    profile.name,
+                    difficulty
+                );
+                assert!((mean - target).abs() <= SUPPORT_TOLERANCE);
+                previous = mean;
+            }
+        }
+    }
+
+    #[test]
+    fn spiral() {
+        let mask = LockingPattern::Spiral.mask(7, 7);
+        let rows = (0..7)
+            .map(|y| {
+                (0..7)
+                    .map(|x| if mask.get(x, y) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            ["#######", "......#", "#####.#", "#...#.#", "#.###.#", "#.....#", "#######"]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! The tuning of the difficulty progression.
+//!
+//! Profiles are loaded from `profiles.json` which is embedded into the game,
+//! so the progression can be retuned without touching the generator.
+
+use super::LockingPattern;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+/// The profile used when none has been picked, and for daily puzzles
+const DEFAULT_PROFILE: &str = "Standard";
+
+lazy_static! {
+    static ref PROFILES: Vec<DifficultyProfile> =
+        serde_json::from_str(include_str!("profiles.json"))
+            .expect("Failed to parse the embedded difficulty profiles");
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DifficultyProfile {
+    pub name: String,
+    /// Cells along the short side of the board at difficulty 1
+    pub min_cells: usize,
+    /// The short side gains `log(difficulty, size_growth)^2` cells, so
+    /// smaller values grow the board faster
+    pub size_growth: f64,
+    /// How far apart (in degrees) the hues of the corners are spread at
+    /// difficulty 1, and the least they're spread at higher difficulties
+    pub max_hue_variance: f64,
+    pub min_hue_variance: f64,
+    /// How many degrees the hue variance shrinks by every level
+    pub hue_variance_step: f64,
+    pub saturation: NormalRange,
+    pub lightness: NormalRange,
+    /// The difficulty at which the progression is half way between its
+    /// easiest and hardest pattern weights and lock support
+    pub progress_midpoint: f64,
+    /// The lock support difficulty aimed for at the start of the progression
+    /// and far into it
+    pub lock_support: (f64, f64),
+    /// The difficulty estimate that generated puzzles are expected to have
+    pub estimate_target: TargetCurve,
+    /// Selection weights of the patterns at the start of the progression and
+    /// far into it
+    pub pattern_weights: Vec<PatternWeight>,
+}
+
+/// A normal distribution with the samples outside of `min..=max` rejected
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NormalRange {
+    pub mean: f64,
+    pub sd: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// A curve that rises from `min` and is half way up `range` at `midpoint`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TargetCurve {
+    pub min: f64,
+    pub range: f64,
+    pub midpoint: f64,
+    pub steepness: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternWeight {
+    /// Patterns with parameters have them randomised when picked
+    pub pattern: LockingPattern,
+    pub easiest: f64,
+    pub hardest: f64,
+}
+
+impl DifficultyProfile {
+    pub fn all() -> &'static [DifficultyProfile] {
+        &PROFILES
+    }
+
+    /// Falls back to the default profile for unknown names
+    pub fn get(name: &str) -> &'static DifficultyProfile {
+        PROFILES
+            .iter()
+            .find(|profile| profile.name == name)
+            .unwrap_or_else(Self::standard)
+    }
+
+    pub fn standard() -> &'static DifficultyProfile {
+        PROFILES
+            .iter()
+            .find(|profile| profile.name == DEFAULT_PROFILE)
+            .expect("The default difficulty profile is missing")
+    }
+
+    /// How far through the progression a difficulty is, rising from 0 towards 1
+    pub fn progress(&self, difficulty: usize) -> f64 {
+        let difficulty = difficulty.max(1) as f64 - 1.0;
+        difficulty / (difficulty + self.progress_midpoint)
+    }
+
+    /// The lock support difficulty that patterns are picked to match
+    pub fn target_support(&self, difficulty: usize) -> f64 {
+        let (easiest, hardest) = self.lock_support;
+        easiest + (hardest - easiest) * self.progress(difficulty)
+    }
+
+    /// The estimated total that puzzles of the given difficulty should have
+    pub fn target_estimate(&self, difficulty: usize) -> f64 {
+        let TargetCurve {
+            min,
+            range,
+            midpoint,
+            steepness,
+        } = self.estimate_target;
+        let difficulty = difficulty.max(1) as f64;
+        min + range / (1.0 + (midpoint / difficulty).powf(steepness))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_profiles_are_valid() {
+        let names = DifficultyProfile::all()
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Casual", "Standard", "Expert"]);
+        assert_eq!(
+            DifficultyProfile::get("Unknown"),
+            DifficultyProfile::standard()
+        );
+
+        for profile in DifficultyProfile::all() {
+            assert!(profile.min_cells >= 2, "{}", profile.name);
+            assert!(profile.min_hue_variance <= profile.max_hue_variance);
+            assert!(profile.max_hue_variance <= 360.0);
+            assert_eq!(profile.pattern_weights.len(), LockingPattern::PRESETS.len());
+            for weight in &profile.pattern_weights {
+                assert!(weight.easiest >= 0.0 && weight.hardest >= 0.0);
+            }
+
+            let targets = [1, 10, 50, 200, 1000]
+                .iter()
+                .map(|d| profile.target_estimate(*d))
+                .collect::<Vec<_>>();
+            assert!(targets.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(targets.iter().all(|&t| t > 0.0 && t < 100.0));
+        }
+    }
+}
@@ -0,0 +1,444 @@
+//! Compact, URL-safe encoding of `PuzzleSettings` used to share puzzles.
+//!
+//! A code is the url-safe base64 of:
+//!
+//! | bytes   | content                                                  |
+//! |---------|----------------------------------------------------------|
+//! | 1       | format version                                           |
+//! | 1       | flags, bit 0 is set for hsl gradients and bit 1 when the |
+//! |         | locks are hidden                                         |
+//! | varint  | difficulty                                               |
+//! | varint  | width                                                    |
+//! | varint  | height                                                   |
+//! | 4 x 3/6 | gradient corners (tl, tr, bl, br) as rgb bytes or as hsl |
+//! |         | u16s in hundredths of a degree and ten thousandths       |
+//! | 1       | locking pattern preset, or 255 followed by a custom mask |
+//! |         | as varint width, varint height and the packed bits       |
+//! | 0-9     | preset parameters, scatter has a density byte and an 8   |
+//! |         | byte seed, every nth row/column has a byte               |
+//! | 8       | shuffle seed (little endian)                             |
+//! | 1       | shuffle strategy, random, swaps, local, rows, columns or |
+//! |         | max displacement                                         |
+//! | 0/var   | strategy parameter as a varint, the number of swaps or   |
+//! |         | the local radius                                         |
+//! | 1       | move kind, swaps, shifts, shifts around locks or         |
+//! |         | adjacent swaps                                           |
+//! | varint  | color tolerance in hundredths of a delta E               |
+//! | 2       | fletcher-16 checksum of all the preceding bytes          |
+
+use super::{LockMask, LockingPattern, MoveKind, PuzzleSettings, SettingsError, ShuffleStrategy};
+use crate::gradient::{Color, Gradient};
+use std::mem::discriminant;
+
+const VERSION: u8 = 1;
+
+const FLAG_HSL: u8 = 1;
+const FLAG_HIDDEN_LOCKS: u8 = 2;
+
+const CUSTOM_PATTERN: u8 = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShareCodeError {
+    Encoding,
+    Checksum,
+    Version(u8),
+    Malformed,
+    /// The code decoded to settings that can't make a puzzle
+    Settings(SettingsError),
+}
+
+impl From<SettingsError> for ShareCodeError {
+    fn from(err: SettingsError) -> Self {
+        Self::Settings(err)
+    }
+}
+
+impl From<base64::DecodeError> for ShareCodeError {
+    fn from(_: base64::DecodeError) -> Self {
+        Self::Encoding
+    }
+}
+
+pub fn encode(settings: &PuzzleSettings) -> String {
+    let corners = settings.gradient.corners();
+    let is_hsl = corners
+        .iter()
+        .all(|color| matches!(color, Color::HSL { .. }));
+
+    let mut flags = 0;
+    if is_hsl {
+        flags |= FLAG_HSL;
+    }
+    if settings.hidden_locks {
+        flags |= FLAG_HIDDEN_LOCKS;
+    }
+
+    let mut bytes = vec![VERSION, flags];
+    write_varint(&mut bytes, settings.difficulty as u64);
+    write_varint(&mut bytes, settings.width as u64);
+    write_varint(&mut bytes, settings.height as u64);
+    for color in corners.iter() {
+        match if is_hsl { *color } else { color.to_rgb() } {
+            Color::RGB { r, g, b } => bytes.extend_from_slice(&[r, g, b]),
+            Color::HSL { h, s, l } => {
+                bytes.extend_from_slice(&((h * 100.0).round() as u16).to_le_bytes());
+                bytes.extend_from_slice(&((s * 10000.0).round() as u16).to_le_bytes());
+                bytes.extend_from_slice(&((l * 10000.0).round() as u16).to_le_bytes());
+            }
+        }
+    }
+    if let LockingPattern::Custom(mask) = &settings.locking_pattern {
+        let (width, height) = mask.dims();
+        bytes.push(CUSTOM_PATTERN);
+        write_varint(&mut bytes, width as u64);
+        write_varint(&mut bytes, height as u64);
+        bytes.extend_from_slice(mask.as_bytes());
+    } else {
+        let pattern = &settings.locking_pattern;
+        let preset = LockingPattern::PRESETS
+            .iter()
+            .position(|preset| discriminant(preset) == discriminant(pattern))
+            .expect("Every locking pattern must have a share code");
+        bytes.push(preset as u8);
+        match pattern {
+            LockingPattern::Scatter { density, seed } => {
+                bytes.push(*density);
+                bytes.extend_from_slice(&seed.to_le_bytes());
+            }
+            LockingPattern::EveryNthRow(n) | LockingPattern::EveryNthColumn(n) => bytes.push(*n),
+            _ => {}
+        }
+    }
+    bytes.extend_from_slice(&settings.shuffle_seed.to_le_bytes());
+    match settings.shuffle {
+        ShuffleStrategy::Random => bytes.push(0),
+        ShuffleStrategy::Swaps(swaps) => {
+            bytes.push(1);
+            write_varint(&mut bytes, u64::from(swaps));
+        }
+        ShuffleStrategy::Local(radius) => {
+            bytes.push(2);
+            write_varint(&mut bytes, u64::from(radius));
+        }
+        ShuffleStrategy::Rows => bytes.push(3),
+        ShuffleStrategy::Columns => bytes.push(4),
+        ShuffleStrategy::MaxDisplacement => bytes.push(5),
+    }
+    bytes.push(match settings.moves {
+        MoveKind::Swaps => 0,
+        MoveKind::Shifts => 1,
+        MoveKind::ShiftsAroundLocks => 2,
+        MoveKind::AdjacentSwaps => 3,
+    });
+    write_varint(
+        &mut bytes,
+        (settings.color_tolerance * 100.0).round().max(0.0) as u64,
+    );
+
+    let checksum = fletcher16(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+
+    base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn decode(code: &str) -> Result<PuzzleSettings, ShareCodeError> {
+    let bytes = base64::decode_config(code.trim(), base64::URL_SAFE_NO_PAD)?;
+    if bytes.len() < 3 {
+        return Err(ShareCodeError::Malformed);
+    }
+
+    let (payload, checksum) = bytes.split_at(bytes.len() - 2);
+    if fletcher16(payload).to_le_bytes() != checksum {
+        return Err(ShareCodeError::Checksum);
+    }
+
+    let mut reader = Reader(payload);
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(ShareCodeError::Version(version));
+    }
+    let flags = reader.byte()?;
+
+    let difficulty = reader.varint()? as usize;
+    let width = reader.varint()? as usize;
+    let height = reader.varint()? as usize;
+    let mut corners = [Color::rgb(0, 0, 0); 4];
+    for corner in corners.iter_mut() {
+        *corner = if flags & FLAG_HSL != 0 {
+            let h = f64::from(reader.u16()?) / 100.0;
+            let s = f64::from(reader.u16()?) / 10000.0;
+            let l = f64::from(reader.u16()?) / 10000.0;
+            if s > 1.0 || l > 1.0 {
+                return Err(ShareCodeError::Malformed);
+            }
+            Color::hsl(h, s, l)
+        } else {
+            Color::rgb(reader.byte()?, reader.byte()?, reader.byte()?)
+        };
+    }
+    let locking_pattern = match reader.byte()? {
+        CUSTOM_PATTERN => {
+            let width = reader.varint()? as usize;
+            let height = reader.varint()? as usize;
+            let cells = width.checked_mul(height).ok_or(ShareCodeError::Malformed)?;
+            let bits = reader.bytes(cells.div_ceil(8))?;
+            let mask = LockMask::from_bytes(width, height, bits.to_vec());
+            LockingPattern::Custom(mask.ok_or(ShareCodeError::Malformed)?)
+        }
+        preset => match LockingPattern::PRESETS.get(preset as usize) {
+            Some(LockingPattern::Scatter { .. }) => LockingPattern::Scatter {
+                density: reader.byte()?,
+                seed: u64::from_le_bytes(reader.array()?),
+            },
+            Some(LockingPattern::EveryNthRow(_)) => LockingPattern::EveryNthRow(reader.byte()?),
+            Some(LockingPattern::EveryNthColumn(_)) => {
+                LockingPattern::EveryNthColumn(reader.byte()?)
+            }
+            Some(pattern) => pattern.clone(),
+            None => return Err(ShareCodeError::Malformed),
+        },
+    };
+    let shuffle_seed = u64::from_le_bytes(reader.array()?);
+    let shuffle = match reader.byte()? {
+        0 => ShuffleStrategy::Random,
+        1 => ShuffleStrategy::Swaps(small_varint(&mut reader)?),
+        2 => ShuffleStrategy::Local(small_varint(&mut reader)?),
+        3 => ShuffleStrategy::Rows,
+        4 => ShuffleStrategy::Columns,
+        5 => ShuffleStrategy::MaxDisplacement,
+        _ => return Err(ShareCodeError::Malformed),
+    };
+    let moves = match reader.byte()? {
+        0 => MoveKind::Swaps,
+        1 => MoveKind::Shifts,
+        2 => MoveKind::ShiftsAroundLocks,
+        3 => MoveKind::AdjacentSwaps,
+        _ => return Err(ShareCodeError::Malformed),
+    };
+    let color_tolerance = reader.varint()? as f64 / 100.0;
+
+    if !reader.0.is_empty() {
+        return Err(ShareCodeError::Malformed);
+    }
+
+    let [top_left, top_right, bottom_left, bottom_right] = corners;
+    let settings = PuzzleSettings {
+        difficulty,
+        width,
+        height,
+        gradient: Gradient::builder()
+            .top_left(top_left)
+            .top_right(top_right)
+            .bottom_left(bottom_left)
+            .bottom_right(bottom_right)
+            .build(),
+        locking_pattern,
+        shuffle,
+        shuffle_seed,
+        color_tolerance,
+        hidden_locks: flags & FLAG_HIDDEN_LOCKS != 0,
+        moves,
+    };
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Reads a varint that has to fit into a smaller integer type
+fn small_varint<T: std::convert::TryFrom<u64>>(reader: &mut Reader) -> Result<T, ShareCodeError> {
+    T::try_from(reader.varint()?).map_err(|_| ShareCodeError::Malformed)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn fletcher16(bytes: &[u8]) -> u16 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for &byte in bytes {
+        sum1 = (sum1 + u16::from(byte)) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (sum2 << 8) | sum1
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, ShareCodeError> {
+        let (&byte, rest) = self.0.split_first().ok_or(ShareCodeError::Malformed)?;
+        self.0 = rest;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ShareCodeError> {
+        if self.0.len() < len {
+            return Err(ShareCodeError::Malformed);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, ShareCodeError> {
+        Ok(u16::from_le_bytes([self.byte()?, self.byte()?]))
+    }
+
+    fn array(&mut self) -> Result<[u8; 8], ShareCodeError> {
+        let mut array = [0; 8];
+        for byte in array.iter_mut() {
+            *byte = self.byte()?;
+        }
+        Ok(array)
+    }
+
+    fn varint(&mut self) -> Result<u64, ShareCodeError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ShareCodeError::Malformed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::puzzle::DifficultyProfile;
+
+    #[test]
+    fn round_trip() {
+        for difficulty in &[1, 50, 1000] {
+            for seed in 0..20 {
+                let settings = PuzzleSettings::from_difficulty(
+                    DifficultyProfile::standard(),
+                    *difficulty,
+                    seed,
+                    (1080, 1920),
+                );
+                let code = encode(&settings);
+                assert!(code.len() <= 64, "code is too long: {}", code);
+                assert_eq!(decode(&code), Ok(settings));
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_custom() {
+        let mut mask = LockMask::new(4, 3);
+        mask.set(0, 0, true);
+        mask.set(3, 2, true);
+        let gradient = Gradient::builder()
+            .top_left(Color::hsl(10.0, 0.5, 0.5))
+            .top_right(Color::hsl(100.0, 0.25, 0.75))
+            .bottom_left(Color::hsl(200.0, 1.0, 0.5))
+            .bottom_right(Color::hsl(300.0, 0.5, 0.4))
+            .build();
+        let pattern = LockingPattern::Custom(mask);
+        let mut settings =
+            PuzzleSettings::custom(4, 3, gradient, pattern, MoveKind::Swaps).unwrap();
+        assert_eq!(decode(&encode(&settings)), Ok(settings.clone()));
+        settings.hidden_locks = true;
+        assert_eq!(decode(&encode(&settings)), Ok(settings.clone()));
+        for &moves in MoveKind::ALL.iter() {
+            settings.moves = moves;
+            assert_eq!(decode(&encode(&settings)), Ok(settings.clone()));
+        }
+    }
+
+    #[test]
+    fn round_trip_parameters() {
+        let patterns = [
+            LockingPattern::Scatter {
+                density: 25,
+                seed: 1234,
+            },
+            LockingPattern::EveryNthRow(3),
+            LockingPattern::EveryNthColumn(4),
+            LockingPattern::Spiral,
+        ];
+        for pattern in patterns.iter() {
+            let mut settings =
+                PuzzleSettings::from_difficulty(DifficultyProfile::standard(), 10, 0, (1080, 1920));
+            settings.locking_pattern = pattern.clone();
+            assert_eq!(decode(&encode(&settings)), Ok(settings));
+        }
+
+        let strategies = [
+            ShuffleStrategy::Random,
+            ShuffleStrategy::Swaps(300),
+            ShuffleStrategy::Local(2),
+            ShuffleStrategy::Rows,
+            ShuffleStrategy::Columns,
+            ShuffleStrategy::MaxDisplacement,
+        ];
+        for strategy in strategies.iter() {
+            let mut settings =
+                PuzzleSettings::from_difficulty(DifficultyProfile::standard(), 10, 0, (1080, 1920));
+            settings.shuffle = *strategy;
+            assert_eq!(decode(&encode(&settings)), Ok(settings));
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_codes() {
+        let settings =
+            PuzzleSettings::from_difficulty(DifficultyProfile::standard(), 10, 0, (1080, 1920));
+        let code = encode(&settings);
+
+        let mut corrupted = code.into_bytes();
+        corrupted[5] = if corrupted[5] == b'A' { b'B' } else { b'A' };
+        let corrupted = String::from_utf8(corrupted).unwrap();
+        assert_eq!(decode(&corrupted), Err(ShareCodeError::Checksum));
+
+        assert_eq!(decode("not a code!"), Err(ShareCodeError::Encoding));
+        assert_eq!(decode(""), Err(ShareCodeError::Malformed));
+
+        let mut tiny = settings;
+        tiny.width = 1;
+        tiny.height = 1;
+        let expected = Err(ShareCodeError::Settings(SettingsError::TooSmall));
+        assert_eq!(decode(&encode(&tiny)), expected);
+    }
+
+    /// Re-encodes the code with one of its bytes changed
+    fn with_byte(code: &str, index: usize, byte: u8) -> String {
+        let mut bytes = base64::decode_config(code, base64::URL_SAFE_NO_PAD).unwrap();
+        bytes.truncate(bytes.len() - 2);
+        bytes[index] = byte;
+        let checksum = fletcher16(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        base64::encode_config(&bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    #[test]
+    fn rejects_unknown_values() {
+        let mut settings =
+            PuzzleSettings::from_difficulty(DifficultyProfile::standard(), 10, 0, (1080, 1920));
+        settings.locking_pattern = LockingPattern::Corners;
+        let code = encode(&settings);
+
+        assert_eq!(
+            decode(&with_byte(&code, 0, 0)),
+            Err(ShareCodeError::Version(0))
+        );
+        let version = VERSION + 1;
+        let expected = Err(ShareCodeError::Version(version));
+        assert_eq!(decode(&with_byte(&code, 0, version)), expected);
+
+        // the preset follows the version, flags, three one byte varints and
+        // the gradient
+        let preset = 5 + 4 * 3;
+        assert_eq!(decode(&with_byte(&code, preset, 0)), Ok(settings));
+        let unknown = LockingPattern::PRESETS.len() as u8;
+        let expected = Err(ShareCodeError::Malformed);
+        assert_eq!(decode(&with_byte(&code, preset, unknown)), expected);
+    }
+}
@@ -0,0 +1,195 @@
+//! Solves boards played with shifts.
+//!
+//! Searching for the fewest shifts is out of reach on boards of any size, so
+//! the board is first checked for being only rotated rows or only rotated
+//! columns, which are put back the shortest way around. Any other board is
+//! solved by undoing the shifts that were made on it, after cancelling out
+//! the ones that undo each other, which isn't always the shortest way.
+
+use super::{Line, Move};
+
+/// Returns shifts that solve the board. `tiles` holds the solved position of
+/// the tile in each cell and `lines` the cells that move when each line is
+/// shifted. `made` are the shifts that led from the solved board to this one.
+pub fn solve<'a, I>(tiles: &[usize], lines: &[(Line, Vec<usize>)], made: I) -> Vec<Move>
+where
+    I: IntoIterator<Item = &'a Move>,
+{
+    let rows = || rotate_back(tiles, lines, |line| matches!(line, Line::Row(_)));
+    let columns = || rotate_back(tiles, lines, |line| matches!(line, Line::Column(_)));
+    rows().or_else(columns).unwrap_or_else(|| {
+        undo_shifts(made, |line| {
+            lines
+                .iter()
+                .find(|&&(other, _)| other == line)
+                .map_or(0, |(_, cells)| cells.len())
+        })
+    })
+}
+
+/// Returns the shifts that put the board back by shifting only the chosen
+/// lines, or None if the board isn't only those lines rotated
+fn rotate_back<F>(tiles: &[usize], lines: &[(Line, Vec<usize>)], chosen: F) -> Option<Vec<Move>>
+where
+    F: Fn(Line) -> bool,
+{
+    let mut covered = vec![false; tiles.len()];
+    let mut solution = Vec::new();
+    for (line, cells) in lines.iter().filter(|&&(line, _)| chosen(line)) {
+        let len = cells.len();
+        // a line shifted forwards `rotation` times holds the tile of the
+        // cell that many cells before each one
+        let first = cells.iter().position(|&cell| cell == tiles[cells[0]])?;
+        let rotation = (len - first) % len;
+        let is_rotated = (0..len).all(|k| tiles[cells[k]] == cells[(k + len - rotation) % len]);
+        if !is_rotated {
+            return None;
+        }
+        for &cell in cells {
+            covered[cell] = true;
+        }
+        let (count, forwards) = if rotation <= len - rotation {
+            (rotation, false)
+        } else {
+            (len - rotation, true)
+        };
+        solution.extend((0..count).map(|_| Move::Shift {
+            line: *line,
+            forwards,
+        }));
+    }
+    let rest_in_place = (0..tiles.len()).all(|cell| covered[cell] || tiles[cell] == cell);
+    Some(solution).filter(|_| rest_in_place)
+}
+
+/// Returns the shifts that take back every one of the made shifts.
+/// `line_len` is the number of tiles that move when a line is shifted.
+fn undo_shifts<'a, I, F>(made: I, line_len: F) -> Vec<Move>
+where
+    I: IntoIterator<Item = &'a Move>,
+    F: Fn(Line) -> usize,
+{
+    // the net rotation of each line, shifts of parallel lines don't touch
+    // the same tiles so they can be reordered to merge ones of the same line
+    let mut rotations: Vec<(Line, usize)> = Vec::new();
+    for mv in made {
+        let (line, forwards) = match *mv {
+            Move::Shift { line, forwards } => (line, forwards),
+            Move::Swap(..) | Move::Cycle(_) => continue,
+        };
+        let len = line_len(line);
+        if len < 2 {
+            continue;
+        }
+        let step = if forwards { 1 } else { len - 1 };
+
+        let merge_with = rotations
+            .iter()
+            .rposition(|&(other, _)| other == line || !is_parallel(other, line))
+            .filter(|&index| rotations[index].0 == line);
+        match merge_with {
+            Some(index) => {
+                let rotation = (rotations[index].1 + step) % len;
+                if rotation == 0 {
+                    rotations.remove(index);
+                } else {
+                    rotations[index].1 = rotation;
+                }
+            }
+            None => rotations.push((line, step)),
+        }
+    }
+
+    // take the rotations back the shortest way around
+    let mut solution = Vec::new();
+    for &(line, rotation) in rotations.iter().rev() {
+        let len = line_len(line);
+        let (count, forwards) = if rotation <= len - rotation {
+            (rotation, false)
+        } else {
+            (len - rotation, true)
+        };
+        solution.extend((0..count).map(|_| Move::Shift { line, forwards }));
+    }
+    solution
+}
+
+fn is_parallel(a: Line, b: Line) -> bool {
+    matches!(
+        (a, b),
+        (Line::Row(_), Line::Row(_)) | (Line::Column(_), Line::Column(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(line: Line, forwards: bool) -> Move {
+        Move::Shift { line, forwards }
+    }
+
+    /// Every row and column of a 4x4 board without locks
+    fn lines() -> Vec<(Line, Vec<usize>)> {
+        let rows = (0..4).map(|y| (Line::Row(y), (0..4).map(|x| y * 4 + x).collect()));
+        let columns = (0..4).map(|x| (Line::Column(x), (0..4).map(|y| y * 4 + x).collect()));
+        rows.chain(columns).collect()
+    }
+
+    #[test]
+    fn rotates_lines_back() {
+        // row 0 shifted forwards once and row 2 three times, which is worked
+        // out from the board alone
+        let mut tiles = (0..16).collect::<Vec<usize>>();
+        tiles[0..4].rotate_right(1);
+        tiles[8..12].rotate_right(3);
+        let solution = solve(&tiles, &lines(), &[]);
+        let expected = [shift(Line::Row(0), false), shift(Line::Row(2), true)];
+        assert_eq!(solution, expected);
+
+        // column 1 shifted backwards twice
+        let mut tiles = (0..16).collect::<Vec<usize>>();
+        let column = [1, 5, 9, 13];
+        for (k, &cell) in column.iter().enumerate() {
+            tiles[cell] = column[(k + 2) % 4];
+        }
+        let solution = solve(&tiles, &lines(), &[]);
+        assert_eq!(solution.len(), 2);
+        assert!(solution.iter().all(|mv| match *mv {
+            Move::Shift { line, .. } => line == Line::Column(1),
+            _ => false,
+        }));
+
+        // a row and a column shifted can only be undone
+        let mut tiles = (0..16).collect::<Vec<usize>>();
+        tiles[0..4].rotate_right(1);
+        tiles.swap(1, 5);
+        let made = [shift(Line::Row(0), true), shift(Line::Column(1), true)];
+        let expected = [shift(Line::Column(1), false), shift(Line::Row(0), false)];
+        assert_eq!(solve(&tiles, &lines(), &made), expected);
+    }
+
+    #[test]
+    fn cancels_out_shifts() {
+        let made = [
+            shift(Line::Row(0), true),
+            shift(Line::Row(1), true),
+            shift(Line::Row(0), false),
+            shift(Line::Column(2), true),
+            shift(Line::Column(2), true),
+            shift(Line::Column(2), true),
+        ];
+        // column 2 is rotated three of four cells, so one more shift is quicker
+        let solution = undo_shifts(&made, |_| 4);
+        let expected = [shift(Line::Column(2), true), shift(Line::Row(1), false)];
+        assert_eq!(solution, expected);
+
+        // a column in between keeps the row shifts apart
+        let made = [
+            shift(Line::Row(0), true),
+            shift(Line::Column(0), true),
+            shift(Line::Row(0), false),
+        ];
+        assert_eq!(undo_shifts(&made, |_| 4).len(), 3);
+    }
+}
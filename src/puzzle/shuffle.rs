@@ -0,0 +1,245 @@
+//! The ways the unlocked tiles of a solved board can be scrambled.
+
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// How many random trades per unlocked tile are tried by the strategies that
+/// improve on an arrangement bit by bit
+const PASSES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShuffleStrategy {
+    /// Every unlocked tile can end up anywhere
+    Random,
+    /// Exactly this many swaps away from solved (or as many as the board
+    /// allows)
+    Swaps(u16),
+    /// Every tile stays within this many cells of its home
+    Local(u8),
+    /// Tiles only move along their row
+    Rows,
+    /// Tiles only move along their column
+    Columns,
+    /// Tiles are moved as far from their homes as possible
+    MaxDisplacement,
+}
+
+impl ShuffleStrategy {
+    /// Picks a strategy for a board with the given number of unlocked tiles.
+    /// Early in the progression the gentler strategies are more likely, later
+    /// on it's mostly fully random and maximum displacement shuffles.
+    pub fn generate<R: Rng>(progress: f64, unlocked: usize, rng: &mut R) -> Self {
+        use ShuffleStrategy::*;
+        let weights = [
+            (1.0 - progress) * 4.0,
+            (1.0 - progress) * 2.0 + 1.0,
+            1.0,
+            1.0,
+            1.0 + progress * 3.0,
+            progress * 2.0,
+        ];
+        match WeightedIndex::new(weights).unwrap().sample(rng) {
+            0 => {
+                let swaps = unlocked as f64 * (0.3 + 0.5 * progress);
+                Swaps((swaps.ceil() as u16).max(1))
+            }
+            1 => Local(1 + (progress * 3.0) as u8),
+            2 => Rows,
+            3 => Columns,
+            4 => Random,
+            _ => MaxDisplacement,
+        }
+    }
+
+    /// Scrambles the unlocked positions of a board, returning the position
+    /// that the tile for each position of the board is taken from
+    pub fn arrange<R: Rng>(
+        self,
+        unlocked: &[usize],
+        (width, height): (usize, usize),
+        rng: &mut R,
+    ) -> Vec<usize> {
+        let mut sources = (0..width * height).collect::<Vec<_>>();
+        let position = |index: usize| ((index % width) as i64, (index / width) as i64);
+        let chebyshev = |a: usize, b: usize| {
+            let ((ax, ay), (bx, by)) = (position(a), position(b));
+            (ax - bx).abs().max((ay - by).abs())
+        };
+        let manhattan = |a: usize, b: usize| {
+            let ((ax, ay), (bx, by)) = (position(a), position(b));
+            (ax - bx).abs() + (ay - by).abs()
+        };
+
+        match self {
+            ShuffleStrategy::Random => {
+                let mut shuffled = unlocked.to_vec();
+                shuffled.shuffle(rng);
+                for (&a, b) in unlocked.iter().zip(shuffled) {
+                    sources.swap(a, b);
+                }
+            }
+            ShuffleStrategy::Swaps(swaps) => {
+                // swapping tiles from different cycles merges the cycles,
+                // which adds exactly one swap to the solution
+                let swaps = usize::from(swaps).min(unlocked.len().saturating_sub(1));
+                for _ in 0..swaps {
+                    let a = *unlocked.choose(rng).unwrap();
+                    let mut in_cycle = vec![false; sources.len()];
+                    let mut index = a;
+                    while !in_cycle[index] {
+                        in_cycle[index] = true;
+                        index = sources[index];
+                    }
+                    let others = unlocked
+                        .iter()
+                        .filter(|&&b| !in_cycle[b])
+                        .collect::<Vec<_>>();
+                    sources.swap(a, **others.choose(rng).unwrap());
+                }
+            }
+            ShuffleStrategy::Local(radius) => {
+                let radius = i64::from(radius.max(1));
+                let mut is_unlocked = vec![false; sources.len()];
+                for &index in unlocked {
+                    is_unlocked[index] = true;
+                }
+                for _ in 0..unlocked.len() * PASSES {
+                    let a = *unlocked.choose(rng).unwrap();
+                    let (ax, ay) = position(a);
+                    let bx = ax + rng.gen_range(-radius, radius + 1);
+                    let by = ay + rng.gen_range(-radius, radius + 1);
+                    if bx < 0 || by < 0 || bx >= width as i64 || by >= height as i64 {
+                        continue;
+                    }
+                    let b = by as usize * width + bx as usize;
+                    if is_unlocked[b]
+                        && chebyshev(sources[a], b) <= radius
+                        && chebyshev(sources[b], a) <= radius
+                    {
+                        sources.swap(a, b);
+                    }
+                }
+            }
+            ShuffleStrategy::Rows | ShuffleStrategy::Columns => {
+                let lines = if self == ShuffleStrategy::Rows {
+                    height
+                } else {
+                    width
+                };
+                for line in 0..lines {
+                    let cells = unlocked
+                        .iter()
+                        .cloned()
+                        .filter(|&index| {
+                            if self == ShuffleStrategy::Rows {
+                                index / width == line
+                            } else {
+                                index % width == line
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    let mut shuffled = cells.clone();
+                    shuffled.shuffle(rng);
+                    for (&index, source) in cells.iter().zip(shuffled) {
+                        sources[index] = source;
+                    }
+                }
+            }
+            ShuffleStrategy::MaxDisplacement => {
+                // start with every tile half way along the board, then trade
+                // tiles whenever that moves them further from home in total
+                let count = unlocked.len();
+                for (i, &index) in unlocked.iter().enumerate() {
+                    sources[index] = unlocked[(i + count / 2) % count.max(1)];
+                }
+                for _ in 0..count * PASSES {
+                    let a = *unlocked.choose(rng).unwrap();
+                    let b = *unlocked.choose(rng).unwrap();
+                    let before = manhattan(sources[a], a) + manhattan(sources[b], b);
+                    let after = manhattan(sources[a], b) + manhattan(sources[b], a);
+                    if after > before {
+                        sources.swap(a, b);
+                    }
+                }
+            }
+        }
+        sources
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fewest swaps that sort the arrangement
+    fn swap_distance(sources: &[usize]) -> usize {
+        let mut visited = vec![false; sources.len()];
+        let mut cycles = 0;
+        for start in 0..sources.len() {
+            if !visited[start] {
+                cycles += 1;
+                let mut index = start;
+                while !visited[index] {
+                    visited[index] = true;
+                    index = sources[index];
+                }
+            }
+        }
+        sources.len() - cycles
+    }
+
+    #[test]
+    fn strategies() {
+        let (width, height) = (8, 6);
+        // every other cell of the first row is locked
+        let unlocked = (0..width * height)
+            .filter(|&index| index >= width || index % 2 == 1)
+            .collect::<Vec<_>>();
+        let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(0);
+
+        let strategies = [
+            ShuffleStrategy::Random,
+            ShuffleStrategy::Swaps(7),
+            ShuffleStrategy::Local(1),
+            ShuffleStrategy::Rows,
+            ShuffleStrategy::Columns,
+            ShuffleStrategy::MaxDisplacement,
+        ];
+        for &strategy in &strategies {
+            let sources = strategy.arrange(&unlocked, (width, height), &mut rng);
+
+            // a permutation of the board that leaves the locked tiles alone
+            let mut sorted = sources.clone();
+            sorted.sort();
+            assert_eq!(sorted, (0..width * height).collect::<Vec<_>>());
+            for (index, &source) in sources.iter().enumerate() {
+                if !unlocked.contains(&index) {
+                    assert_eq!(source, index, "{:?}", strategy);
+                }
+            }
+            assert!(swap_distance(&sources) > 0, "{:?}", strategy);
+
+            for (index, &source) in sources.iter().enumerate() {
+                let (x, y) = (index % width, index / width);
+                let (sx, sy) = (source % width, source / width);
+                match strategy {
+                    ShuffleStrategy::Local(radius) => {
+                        let radius = usize::from(radius);
+                        assert!(x.max(sx) - x.min(sx) <= radius);
+                        assert!(y.max(sy) - y.min(sy) <= radius);
+                    }
+                    ShuffleStrategy::Rows => assert_eq!(y, sy),
+                    ShuffleStrategy::Columns => assert_eq!(x, sx),
+                    _ => {}
+                }
+            }
+        }
+
+        for swaps in 1..20 {
+            let sources =
+                ShuffleStrategy::Swaps(swaps).arrange(&unlocked, (width, height), &mut rng);
+            assert_eq!(swap_distance(&sources), usize::from(swaps));
+        }
+    }
+}
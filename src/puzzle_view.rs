@@ -1,17 +1,70 @@
+use crate::clock;
 use crate::debug;
-use crate::puzzle::{Puzzle, PuzzleCell};
-use crate::savegame;
-use crate::SAVEGAME_KEY;
+use crate::puzzle::{Line, Lock, Move, MoveKind, Puzzle, PuzzleCell, HINTS_PER_LEVEL};
+use crate::share_link;
+use std::time::Duration;
+use stdweb::traits::{IEvent, IKeyboardEvent, IMouseEvent};
+use stdweb::web::document;
+use stdweb::web::event::{
+    KeyDownEvent, KeyUpEvent, PointerCancelEvent, PointerDownEvent, PointerLeaveEvent,
+    PointerUpEvent,
+};
 use yew::prelude::*;
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
+use yew::services::timeout::{TimeoutService, TimeoutTask};
+
+/// How long a hidden lock shakes for when it's touched
+const ANCHOR_SHAKE_DURATION: Duration = Duration::from_millis(400);
+
+/// How long tiles locked by the auto-lock assist are highlighted for
+const EARNED_LOCK_FLASH_DURATION: Duration = Duration::from_millis(600);
+
+/// How far (in pixels) a pointer has to be dragged to shift a line
+const DRAG_THRESHOLD: f64 = 10.0;
 
 pub struct PuzzleView {
     props: Props,
     link: ComponentLink<Self>,
-    active_tile: Option<usize>,
+    /// The tiles picked so far, in order. Two make a swap, more than that
+    /// are cycled.
+    selected: Vec<usize>,
+    /// Taps add tiles to the selection rather than finishing the move, for
+    /// when shift can't be held
+    chaining: bool,
+    hint: Option<Move>,
+    link_copied: bool,
+    /// The tile a drag started on and where the pointer was, on boards
+    /// played with shifts
+    drag_start: Option<(usize, f64, f64)>,
+    /// A hidden lock that was touched, and the timer that stops it shaking
+    anchored: Option<(usize, TimeoutTask)>,
+    /// Tiles the auto-lock assist just locked, and the timer that ends their
+    /// highlight
+    just_locked: Option<(Vec<usize>, TimeoutTask)>,
+    /// The solved board is shown while the peek button or key is held
+    peeking: bool,
+    _key_listener: KeyListenerHandle,
+    _key_up_listener: KeyListenerHandle,
 }
 
 pub enum Msg {
-    TouchTile(usize),
+    /// The tile, and whether it's added to the selection
+    TouchTile(usize, bool),
+    ToggleChaining,
+    StartDrag(usize, f64, f64),
+    EndDrag(f64, f64),
+    /// The pointer left the board or was taken away before the drag ended
+    CancelDrag,
+    StopShaking,
+    EndLockHighlight,
+    ShowHint,
+    Undo,
+    Redo,
+    KeyDown(KeyDownEvent),
+    KeyUp(KeyUpEvent),
+    StartPeek,
+    EndPeek,
+    CopyLink,
 }
 
 #[derive(Clone, Properties)]
@@ -19,7 +72,31 @@ pub struct Props {
     #[props(required)]
     pub puzzle: Puzzle,
     #[props(required)]
-    pub oncomplete: Callback<()>,
+    pub save_key: &'static str,
+    /// How many moves the board may take, if it's limited
+    pub move_limit: Option<usize>,
+    /// How many times the solved board may be peeked at, if it's limited
+    pub peek_limit: Option<usize>,
+    /// The board isn't scored, so its moves aren't shown and its hints
+    /// aren't limited
+    pub unscored: bool,
+    /// The board can be played. The HUD is hidden and keys are ignored while
+    /// it can't, like while a memory board is being studied.
+    pub active: bool,
+    /// The locks were shown while a memory board was studied and have to be
+    /// remembered from then on. Unlike hidden locks this doesn't change how
+    /// the board is rated.
+    pub memory_hidden: bool,
+    /// Bumped to restart a board that has the same settings
+    pub attempt: usize,
+    /// Called with the number of tiles in place after every move
+    #[props(required)]
+    pub onmove: Callback<usize>,
+    #[props(required)]
+    pub oncomplete: Callback<Puzzle>,
+    /// Called when the board runs out of moves without being solved
+    #[props(required)]
+    pub onfail: Callback<Puzzle>,
 }
 
 impl Component for PuzzleView {
@@ -27,41 +104,171 @@ impl Component for PuzzleView {
     type Properties = Props;
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let key_listener =
+            KeyboardService::register_key_down(&document(), link.callback(Msg::KeyDown));
+        let key_up_listener =
+            KeyboardService::register_key_up(&document(), link.callback(Msg::KeyUp));
         Self {
             props,
             link,
-            active_tile: None,
+            selected: Vec::new(),
+            chaining: false,
+            hint: None,
+            link_copied: false,
+            drag_start: None,
+            anchored: None,
+            just_locked: None,
+            peeking: false,
+            _key_listener: key_listener,
+            _key_up_listener: key_up_listener,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
         match msg {
-            Msg::TouchTile(index) => {
-                // ignore locked tiles
-                if self.props.puzzle.get(index).is_locked {
+            Msg::TouchTile(index, extend) => {
+                // locked tiles can't be moved, hidden anchors shake to give
+                // themselves away
+                let cell = self.props.puzzle.get(index);
+                if cell.is_locked() {
+                    if self.shows_lock(cell) {
+                        return false;
+                    }
+                    let stop = self.link.callback(|_| Msg::StopShaking);
+                    let task = TimeoutService::new().spawn(ANCHOR_SHAKE_DURATION, stop);
+                    self.anchored = Some((index, task));
+                    return true;
+                }
+                // lines are shifted by dragging instead
+                if self.props.puzzle.settings.moves().shifts_lines() {
                     return false;
                 }
+                self.props.puzzle.stats.start_timer(clock::now_ms());
 
-                if let Some(active_tile) = self.active_tile {
-                    if active_tile != index {
-                        self.props.puzzle.swap(active_tile, index);
-                        savegame::save(SAVEGAME_KEY, &self.props.puzzle);
-                        if self.props.puzzle.is_solved() {
-                            self.props.oncomplete.emit(());
-                        }
+                // only boards where any two tiles can swap can cycle them
+                let extend = (extend || self.chaining)
+                    && self.props.puzzle.settings.moves() == MoveKind::Swaps;
+                match self.selected.iter().position(|&tile| tile == index) {
+                    // going back to the first tile closes the cycle
+                    Some(0) if self.selected.len() > 1 => {
+                        let cells = std::mem::take(&mut self.selected);
+                        self.make_move(Move::rotation(cells));
+                    }
+                    Some(_) => self.selected.clear(),
+                    None if extend || self.selected.is_empty() => self.selected.push(index),
+                    None => {
+                        let mut cells = std::mem::take(&mut self.selected);
+                        cells.push(index);
+                        self.make_move(Move::rotation(cells));
                     }
-                    self.active_tile = None;
+                }
+                true
+            }
+            Msg::ToggleChaining => {
+                self.chaining = !self.chaining;
+                if !self.chaining && self.selected.len() > 1 {
+                    self.selected.clear();
+                }
+                true
+            }
+            Msg::StartDrag(index, x, y) => {
+                if self.props.puzzle.settings.moves().shifts_lines() {
+                    self.drag_start = Some((index, x, y));
+                }
+                false
+            }
+            Msg::EndDrag(x, y) => {
+                let (index, start_x, start_y) = match self.drag_start.take() {
+                    Some(start) => start,
+                    None => return false,
+                };
+                let (dx, dy) = (x - start_x, y - start_y);
+                if dx.abs().max(dy.abs()) < DRAG_THRESHOLD {
+                    return false;
+                }
+                let (width, _) = self.props.puzzle.dimensions();
+                let shift = if dx.abs() > dy.abs() {
+                    Move::Shift {
+                        line: Line::Row(index / width),
+                        forwards: dx > 0.0,
+                    }
+                } else {
+                    Move::Shift {
+                        line: Line::Column(index % width),
+                        forwards: dy > 0.0,
+                    }
+                };
+                self.props.puzzle.stats.start_timer(clock::now_ms());
+                self.make_move(shift)
+            }
+            Msg::CancelDrag => {
+                self.drag_start = None;
+                false
+            }
+            Msg::StopShaking => {
+                self.anchored = None;
+                true
+            }
+            Msg::EndLockHighlight => {
+                self.just_locked = None;
+                true
+            }
+            Msg::Undo => self.apply_history(Puzzle::undo, Puzzle::next_undo),
+            Msg::Redo => self.apply_history(Puzzle::redo, Puzzle::next_redo),
+            Msg::KeyDown(_) if !self.props.active => false,
+            Msg::KeyDown(event) => {
+                // Ctrl+Z to undo and Ctrl+Shift+Z to redo (Cmd instead of Ctrl on macs)
+                if (event.ctrl_key() || event.meta_key()) && event.key().to_lowercase() == "z" {
+                    event.prevent_default();
+                    if event.shift_key() {
+                        self.apply_history(Puzzle::redo, Puzzle::next_redo)
+                    } else {
+                        self.apply_history(Puzzle::undo, Puzzle::next_undo)
+                    }
+                } else if is_peek_key(&event.key())
+                    && !(event.ctrl_key() || event.meta_key() || event.repeat())
+                {
+                    self.start_peek()
                 } else {
-                    self.active_tile = Some(index);
+                    false
                 }
+            }
+            // a peek that was started before the board stopped being played
+            // still ends when the key is let go
+            Msg::KeyUp(event) => is_peek_key(&event.key()) && self.end_peek(),
+            Msg::StartPeek => self.start_peek(),
+            Msg::EndPeek => self.end_peek(),
+            Msg::CopyLink => {
+                share_link::copy_to_clipboard(&self.props.puzzle.share_code());
+                self.link_copied = true;
                 true
             }
+            Msg::ShowHint => {
+                if let Some(hint) = self.props.puzzle.use_hint(self.hint_limit()) {
+                    self.props.puzzle.save(self.props.save_key);
+                    self.selected.clear();
+                    self.hint = Some(hint);
+                    true
+                } else {
+                    false
+                }
+            }
         }
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        if self.props.puzzle.settings != props.puzzle.settings {
+        if self.props.puzzle.settings != props.puzzle.settings
+            || self.props.puzzle.auto_lock() != props.puzzle.auto_lock()
+            || self.props.attempt != props.attempt
+        {
             self.props = props;
+            self.selected.clear();
+            self.hint = None;
+            self.link_copied = false;
+            self.drag_start = None;
+            self.anchored = None;
+            self.just_locked = None;
+            self.peeking = false;
             true
         } else {
             false
@@ -69,40 +276,413 @@ impl Component for PuzzleView {
     }
 
     fn view(&self) -> Html {
-        let (width, height) = self.props.puzzle.dimensions();
+        html! {
+            <>
+                { self.board() }
+                { self.peek_cover() }
+                { self.hud() }
+            </>
+        }
+    }
+}
 
+impl PuzzleView {
+    fn board(&self) -> Html {
+        let (width, height) = self.props.puzzle.dimensions();
         html! {
             <div
                 class="grid"
-                style=format!("--grid-width: {}; --grid-height: {}", width, height)>
-            {
-                self.props.puzzle.iter().enumerate()
-                    .map(|(i, cell)| {
-                        let is_active = Some(i) == self.active_tile;
-                        let onclick = self.link.callback(move |_| Msg::TouchTile(i));
-                        color_tile(cell, is_active, onclick)
-                    })
-                    .collect::<Html>()
+                style=format!("--grid-width: {}; --grid-height: {}", width, height)
+                onpointerup=self.link.callback(|event: PointerUpEvent| {
+                    Msg::EndDrag(f64::from(event.client_x()), f64::from(event.client_y()))
+                })
+                onpointerleave=self.link.callback(|_: PointerLeaveEvent| Msg::CancelDrag)
+                onpointercancel=self.link.callback(|_: PointerCancelEvent| Msg::CancelDrag)>
+                { for self.props.puzzle.iter().enumerate().map(|(i, cell)| self.tile(i, cell)) }
+            </div>
+        }
+    }
+
+    fn tile(&self, i: usize, cell: &PuzzleCell) -> Html {
+        let order = self.selected.iter().position(|&tile| tile == i);
+        let selection = order.map(|order| (order, self.selected.len()));
+        let onclick = self
+            .link
+            .callback(move |event: ClickEvent| Msg::TouchTile(i, event.shift_key()));
+        let onpointerdown = self.link.callback(move |event: PointerDownEvent| {
+            let (x, y) = (event.client_x(), event.client_y());
+            Msg::StartDrag(i, f64::from(x), f64::from(y))
+        });
+        let show_lock = self.shows_lock(cell);
+        let flash = match (&self.anchored, &self.just_locked) {
+            (Some((anchor, _)), _) if *anchor == i => Some("anchored"),
+            (_, Some((tiles, _))) if tiles.contains(&i) => Some("just-locked"),
+            _ => None,
+        };
+        color_tile(
+            cell,
+            show_lock,
+            selection,
+            flash,
+            self.hint_direction(i),
+            onclick,
+            onpointerdown,
+        )
+    }
+
+    /// Makes a move on the board and lets the game know how it went
+    fn make_move(&mut self, mv: Move) -> ShouldRender {
+        let out_of_moves = !self.props.puzzle.can_afford(&mv, self.props.move_limit);
+        let was_locked = self
+            .props
+            .puzzle
+            .iter()
+            .map(PuzzleCell::is_locked)
+            .collect::<Vec<_>>();
+        if out_of_moves || !self.props.puzzle.make_move(mv) {
+            return false;
+        }
+        let just_locked = self
+            .props
+            .puzzle
+            .iter()
+            .enumerate()
+            .filter(|&(index, cell)| cell.is_locked() && !was_locked[index])
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        if !just_locked.is_empty() {
+            let end = self.link.callback(|_| Msg::EndLockHighlight);
+            let task = TimeoutService::new().spawn(EARNED_LOCK_FLASH_DURATION, end);
+            self.just_locked = Some((just_locked, task));
+        }
+        self.hint = None;
+        self.link_copied = false;
+        let is_solved = self.props.puzzle.is_solved();
+        if is_solved {
+            self.props.puzzle.stats.stop_timer(clock::now_ms());
+        }
+        self.props.puzzle.save(self.props.save_key);
+        self.props.onmove.emit(self.props.puzzle.correctly_placed());
+        if is_solved {
+            self.props.oncomplete.emit(self.props.puzzle.clone());
+        } else {
+            self.check_move_limit();
+        }
+        true
+    }
+
+    /// Steps through the move history with either `Puzzle::undo` or `Puzzle::redo`,
+    /// `next` being the move that the step makes or takes back
+    fn apply_history(
+        &mut self,
+        step: fn(&mut Puzzle) -> bool,
+        next: fn(&Puzzle) -> Option<&Move>,
+    ) -> ShouldRender {
+        // a solved puzzle is finished, it can't be taken back anymore. Taking
+        // back a cycle costs as many moves as making it did.
+        let puzzle = &self.props.puzzle;
+        let affordable =
+            next(puzzle).is_some_and(|mv| puzzle.can_afford(mv, self.props.move_limit));
+        if puzzle.is_solved() || !affordable || !step(&mut self.props.puzzle) {
+            return false;
+        }
+        self.selected.clear();
+        self.hint = None;
+        self.props.puzzle.save(self.props.save_key);
+        self.props.onmove.emit(self.props.puzzle.correctly_placed());
+        self.check_move_limit();
+        true
+    }
+
+    /// Whether the tile is drawn with a lock, memory boards hide their anchors
+    /// once they've been studied
+    fn shows_lock(&self, cell: &PuzzleCell) -> bool {
+        self.props.puzzle.shows_lock(cell) && !(self.props.memory_hidden && cell.is_anchor())
+    }
+
+    fn moves_remaining(&self) -> Option<usize> {
+        self.props.puzzle.moves_remaining(self.props.move_limit)
+    }
+
+    /// Fails the board once it's out of moves
+    fn check_move_limit(&self) {
+        let puzzle = &self.props.puzzle;
+        if puzzle.is_out_of_moves(self.props.move_limit) && !puzzle.is_solved() {
+            self.props.onfail.emit(self.props.puzzle.clone());
+        }
+    }
+
+    /// If the tile is part of the current hint, returns the unit vector
+    /// pointing towards the cell it should be moved to
+    fn hint_direction(&self, index: usize) -> Option<(f64, f64)> {
+        let (a, b) = match *self.hint.as_ref()? {
+            Move::Swap(a, b) => (a, b),
+            Move::Cycle(ref cells) => {
+                let order = cells.iter().position(|&cell| cell == index)?;
+                (index, cells[(order + 1) % cells.len()])
             }
+            Move::Shift { line, forwards } => {
+                let cells = self.props.puzzle.shifted_cells(line)?;
+                if !cells.contains(&index) {
+                    return None;
+                }
+                let step = if forwards { 1.0 } else { -1.0 };
+                return match line {
+                    Line::Row(_) => Some((step, 0.0)),
+                    Line::Column(_) => Some((0.0, step)),
+                };
+            }
+        };
+        let (from, to) = if index == a {
+            (a, b)
+        } else if index == b {
+            (b, a)
+        } else {
+            return None;
+        };
+
+        let (width, _) = self.props.puzzle.dimensions();
+        let dx = (to % width) as f64 - (from % width) as f64;
+        let dy = (to / width) as f64 - (from / width) as f64;
+        let length = dx.hypot(dy);
+        Some((dx / length, dy / length))
+    }
+
+    fn hud(&self) -> Html {
+        if !self.props.active {
+            return html! {};
+        }
+        html! {
+            <div class="hud">
+                { self.moves_label() }
+                { self.history_buttons() }
+                { self.hint_button() }
+                { self.peek_button() }
+                { self.cycle_button() }
+                <div class="hud-button" onclick=self.link.callback(|_| Msg::CopyLink)>
+                    { if self.link_copied { "Copied!" } else { "Copy Link" } }
+                </div>
+            </div>
+        }
+    }
+
+    fn moves_label(&self) -> Html {
+        if self.props.unscored {
+            return html! {};
+        }
+        let par = self.props.puzzle.par();
+        let label = match self.moves_remaining() {
+            Some(remaining) => format!("Moves left: {} / Par: {}", remaining, par),
+            None => format!("Moves: {} / Par: {}", self.props.puzzle.stats.moves, par),
+        };
+        html! {
+            <div class="hud-label">{label}</div>
+        }
+    }
+
+    fn history_buttons(&self) -> Html {
+        let button_class = |enabled| {
+            if enabled {
+                "hud-button"
+            } else {
+                "hud-button disabled"
+            }
+        };
+        let puzzle = &self.props.puzzle;
+        let affordable =
+            |mv: Option<&Move>| mv.is_some_and(|mv| puzzle.can_afford(mv, self.props.move_limit));
+        html! {
+            <>
+                <div
+                    class=button_class(affordable(puzzle.next_undo()))
+                    title="Undo (Ctrl+Z)"
+                    onclick=self.link.callback(|_| Msg::Undo)>
+                    {"Undo"}
+                </div>
+                <div
+                    class=button_class(affordable(puzzle.next_redo()))
+                    title="Redo (Ctrl+Shift+Z)"
+                    onclick=self.link.callback(|_| Msg::Redo)>
+                    {"Redo"}
+                </div>
+            </>
+        }
+    }
+
+    fn hint_limit(&self) -> Option<usize> {
+        if self.props.unscored {
+            None
+        } else {
+            Some(HINTS_PER_LEVEL)
+        }
+    }
+
+    fn hint_button(&self) -> Html {
+        let (class, label) = match self.props.puzzle.hints_remaining(self.hint_limit()) {
+            Some(0) => ("hud-button disabled", "Hint (0)".to_string()),
+            Some(remaining) => ("hud-button", format!("Hint ({})", remaining)),
+            None => ("hud-button", "Hint".to_string()),
+        };
+        html! {
+            <div class=class onclick=self.link.callback(|_| Msg::ShowHint)>
+                {label}
+            </div>
+        }
+    }
+
+    /// Shows the solved board for as long as the button is held
+    fn peek_button(&self) -> Html {
+        let remaining = self
+            .props
+            .peek_limit
+            .map(|limit| limit.saturating_sub(self.props.puzzle.stats.peeks_used));
+        let (class, label) = match remaining {
+            Some(0) => ("hud-button disabled", "Peek (0)".to_string()),
+            Some(remaining) => ("hud-button", format!("Peek ({})", remaining)),
+            None => ("hud-button", "Peek".to_string()),
+        };
+        html! {
+            <div
+                class=class
+                title="Hold to see the solved board (or hold P)"
+                onpointerdown=self.link.callback(|_: PointerDownEvent| Msg::StartPeek)
+                onpointerup=self.link.callback(|_: PointerUpEvent| Msg::EndPeek)
+                onpointerleave=self.link.callback(|_: PointerLeaveEvent| Msg::EndPeek)>
+                {label}
+            </div>
+        }
+    }
+
+    /// The solved board, drawn from the settings over the one being played
+    fn peek_cover(&self) -> Html {
+        if self.peeking {
+            let settings = &self.props.puzzle.settings;
+            let show_locks = !settings.hidden_locks() && !self.props.memory_hidden;
+            static_grid(&Puzzle::unshuffled(settings.clone()), "peeking", show_locks)
+        } else {
+            html! {}
+        }
+    }
+
+    fn start_peek(&mut self) -> ShouldRender {
+        if self.peeking || !self.props.puzzle.use_peek(self.props.peek_limit) {
+            return false;
+        }
+        self.props.puzzle.save(self.props.save_key);
+        self.peeking = true;
+        true
+    }
+
+    fn end_peek(&mut self) -> ShouldRender {
+        std::mem::replace(&mut self.peeking, false)
+    }
+
+    /// Toggles picking several tiles to cycle, which can also be done by
+    /// holding shift
+    fn cycle_button(&self) -> Html {
+        if self.props.puzzle.settings.moves() != MoveKind::Swaps {
+            return html! {};
+        }
+        let class = if self.chaining {
+            "hud-button selected"
+        } else {
+            "hud-button"
+        };
+        html! {
+            <div
+                class=class
+                title="Pick tiles to cycle, then the first one again (or Shift+click)"
+                onclick=self.link.callback(|_| Msg::ToggleChaining)>
+                {"Cycle"}
             </div>
         }
     }
 }
 
-fn color_tile(cell: &PuzzleCell, is_active: bool, onclick: Callback<ClickEvent>) -> Html {
-    let class = match (is_active, cell.is_locked) {
+fn is_peek_key(key: &str) -> bool {
+    key.eq_ignore_ascii_case("p")
+}
+
+/// A board that can't be played, shown on top of the real one. Every tile
+/// knows how far it is from its solved position so it can be animated from
+/// there. The anchors are only drawn with locks if `show_locks` is set.
+pub fn static_grid(puzzle: &Puzzle, class: &str, show_locks: bool) -> Html {
+    let (width, height) = puzzle.dimensions();
+    html! {
+        <div
+            class=format!("grid {}", class)
+            style=format!("--grid-width: {}; --grid-height: {}", width, height)>
+            {
+                for puzzle.iter().enumerate().map(|(index, cell)| {
+                    let from = cell.solved_position;
+                    let style = format!(
+                        "background: {}; --from-dx: {}; --from-dy: {}",
+                        cell.color.to_css(),
+                        (from % width) as f64 - (index % width) as f64,
+                        (from / width) as f64 - (index / width) as f64,
+                    );
+                    html! {
+                        <div class="cell">
+                            <div class="tile" style=style>
+                                {
+                                    if show_locks && cell.is_anchor() {
+                                        html! {<div class="lock" />}
+                                    } else {
+                                        html! {}
+                                    }
+                                }
+                            </div>
+                        </div>
+                    }
+                })
+            }
+        </div>
+    }
+}
+
+/// `selection` is where the tile is in the selection and how many tiles are
+/// selected, tiles being cycled are numbered in the order they were picked.
+/// `flash` is the class of a short animation the tile is playing.
+fn color_tile(
+    cell: &PuzzleCell,
+    show_lock: bool,
+    selection: Option<(usize, usize)>,
+    flash: Option<&str>,
+    hint_direction: Option<(f64, f64)>,
+    onclick: Callback<ClickEvent>,
+    onpointerdown: Callback<PointerDownEvent>,
+) -> Html {
+    // hidden locks look like any other tile, even on hover
+    let mut class = match (selection.is_some(), show_lock) {
         (true, true) => "cell active locked",
         (true, false) => "cell active interactive",
         (false, true) => "cell locked",
         (false, false) => "cell interactive",
-    };
-    let style = format!("background: {}", cell.color.to_css());
+    }
+    .to_string();
+    let mut style = format!("background: {}", cell.color.to_css());
+    if cell.lock == Some(Lock::Earned) {
+        class.push_str(" earned");
+    }
+    if let Some(flash) = flash {
+        class.push(' ');
+        class.push_str(flash);
+    }
+    if let Some((dx, dy)) = hint_direction {
+        class.push_str(" hint");
+        style.push_str(&format!("; --hint-dx: {}; --hint-dy: {}", dx, dy));
+    }
+    let cycle_order = selection
+        .filter(|&(_, selected)| selected > 1)
+        .map(|(order, _)| order + 1);
     html! {
-        <div class=class onclick=onclick>
+        <div class=class onclick=onclick onpointerdown=onpointerdown>
             <div class="tile" style=style>
                 {
-                    if cell.is_locked {
+                    if show_lock {
                         html! {<div class="lock" />}
+                    } else if let Some(order) = cycle_order {
+                        html! {<div class="cycle-order">{order}</div>}
                     } else if debug::show_cell_numbers() {
                         html! {<div>{cell.solved_position}</div>}
                     } else {
@@ -3,6 +3,10 @@ use bincode;
 use serde::{Deserialize, Serialize};
 use stdweb::{js, unstable::TryInto};
 
+/// Versioned saves start with this tag. Saves from before they were versioned
+/// start with the difficulty of their puzzle, which never gets this high.
+const VERSIONED_TAG: u64 = u64::MAX;
+
 pub struct SaveGameError;
 
 impl From<bincode::Error> for SaveGameError {
@@ -17,6 +21,14 @@ impl From<base64::DecodeError> for SaveGameError {
     }
 }
 
+/// A game along with the version of the layout it was saved in
+#[derive(Serialize, Deserialize)]
+struct Versioned<T> {
+    tag: u64,
+    version: u32,
+    game: T,
+}
+
 fn encode<T>(game: T) -> Result<String, SaveGameError>
 where
     T: Serialize,
@@ -33,12 +45,42 @@ where
     Ok(bincode::deserialize::<T>(&bin)?)
 }
 
+pub fn encode_versioned<T>(version: u32, game: T) -> Result<Vec<u8>, SaveGameError>
+where
+    T: Serialize,
+{
+    let versioned = Versioned {
+        tag: VERSIONED_TAG,
+        version,
+        game,
+    };
+    Ok(bincode::serialize(&versioned)?)
+}
+
+/// Decodes a game saved with `encode_versioned` in the given version. Saves
+/// from before saves were versioned are handed to `migrate` as they are, and
+/// saves of any other version are dropped.
+pub fn decode_versioned<T, F>(bin: &[u8], version: u32, migrate: F) -> Option<T>
+where
+    T: for<'a> Deserialize<'a>,
+    F: FnOnce(&[u8]) -> Option<T>,
+{
+    match bincode::deserialize::<(u64, u32)>(bin) {
+        Ok((VERSIONED_TAG, saved)) if saved == version => {
+            let versioned = bincode::deserialize::<Versioned<T>>(bin).ok()?;
+            Some(versioned.game)
+        }
+        Ok((VERSIONED_TAG, _)) => None,
+        _ => migrate(bin),
+    }
+}
+
 pub fn save<T>(key: &str, game: T)
 where
     T: Serialize,
 {
     if let Ok(encoded) = encode(game) {
-        js! { localStorage.setItem(@{key}, @{encoded}) };
+        store(key, encoded);
     }
 }
 
@@ -46,6 +88,31 @@ pub fn load<T>(key: &str) -> Option<T>
 where
     T: for<'a> Deserialize<'a>,
 {
-    let encoded = js! { return localStorage.getItem(@{key}); }.try_into().ok();
-    encoded.and_then(|encoded| decode(encoded).ok())
+    fetch(key).and_then(|encoded| decode(encoded).ok())
+}
+
+pub fn save_versioned<T>(key: &str, version: u32, game: T)
+where
+    T: Serialize,
+{
+    if let Ok(bin) = encode_versioned(version, game) {
+        store(key, base64::encode(&bin));
+    }
+}
+
+pub fn load_versioned<T, F>(key: &str, version: u32, migrate: F) -> Option<T>
+where
+    T: for<'a> Deserialize<'a>,
+    F: FnOnce(&[u8]) -> Option<T>,
+{
+    let bin = base64::decode(&fetch(key)?).ok()?;
+    decode_versioned(&bin, version, migrate)
+}
+
+fn store(key: &str, encoded: String) {
+    js! { localStorage.setItem(@{key}, @{encoded}) };
+}
+
+fn fetch(key: &str) -> Option<String> {
+    js! { return localStorage.getItem(@{key}); }.try_into().ok()
 }
@@ -0,0 +1,30 @@
+use stdweb::{js, unstable::TryInto};
+
+const FRAGMENT_PREFIX: &str = "#p=";
+
+/// Reads the share code from a `#p=<code>` url fragment
+pub fn code_from_url() -> Option<String> {
+    let hash: String = js! { return location.hash; }.try_into().ok()?;
+    hash.strip_prefix(FRAGMENT_PREFIX).map(str::to_string)
+}
+
+/// Removes the share code from the url without reloading the page
+pub fn clear_url() {
+    js! { history.replaceState(null, "", location.pathname + location.search); }
+}
+
+pub fn copy_to_clipboard(code: &str) {
+    let fragment = format!("{}{}", FRAGMENT_PREFIX, code);
+    let url: String =
+        js! { return location.origin + location.pathname + location.search + @{fragment}; }
+            .try_into()
+            .expect("Failed to build the share link");
+    copy_text(&url);
+}
+
+pub fn copy_text(text: &str) {
+    js! {
+        const text = @{text};
+        navigator.clipboard.writeText(text).catch(() => window.prompt("Copy this", text));
+    }
+}
@@ -0,0 +1,86 @@
+use crate::savegame;
+use serde::{Deserialize, Serialize};
+
+/// Stats tracked while a level is being played
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LevelStats {
+    pub hints_used: usize,
+    /// How many times the solved board was peeked at
+    pub peeks_used: usize,
+    /// Every swap counts as a move, including undos and redos
+    pub moves: usize,
+    /// Timestamps (in ms) of the first touch and of the solve
+    pub started_at: Option<f64>,
+    pub finished_at: Option<f64>,
+    /// The auto-lock assist locked at least one tile
+    pub auto_locked: bool,
+}
+
+impl LevelStats {
+    pub fn start_timer(&mut self, now: f64) {
+        self.started_at.get_or_insert(now);
+    }
+
+    pub fn stop_timer(&mut self, now: f64) {
+        self.finished_at.get_or_insert(now);
+    }
+
+    pub fn solve_time_ms(&self) -> Option<f64> {
+        Some(self.finished_at? - self.started_at?)
+    }
+}
+
+/// Stats of a completed level, kept in the savegame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub difficulty: usize,
+    pub par: usize,
+    pub stars: usize,
+    pub stats: LevelStats,
+}
+
+pub const MAX_STARS: usize = 3;
+
+/// Rates a solve from one to three stars based on how close it was to par
+pub fn star_rating(moves: usize, par: usize) -> usize {
+    if moves <= par {
+        3
+    } else if moves <= par + (par / 2).max(2) {
+        2
+    } else {
+        1
+    }
+}
+
+pub fn format_stars(stars: usize) -> String {
+    let filled = stars.min(MAX_STARS);
+    "\u{2605}".repeat(filled) + &"\u{2606}".repeat(MAX_STARS - filled)
+}
+
+pub fn load_records(key: &str) -> Vec<LevelRecord> {
+    savegame::load(key).unwrap_or_default()
+}
+
+pub fn add_record(key: &str, record: LevelRecord) {
+    let mut records = load_records(key);
+    records.push(record);
+    savegame::save(key, &records);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn star_rating_thresholds() {
+        assert_eq!(star_rating(10, 10), 3);
+        assert_eq!(star_rating(11, 10), 2);
+        assert_eq!(star_rating(15, 10), 2);
+        assert_eq!(star_rating(16, 10), 1);
+
+        // small pars still allow two extra moves for two stars
+        assert_eq!(star_rating(0, 0), 3);
+        assert_eq!(star_rating(3, 1), 2);
+        assert_eq!(star_rating(4, 1), 1);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::puzzle::Puzzle;
+use crate::savegame;
+use serde::{Deserialize, Serialize};
+
+/// Every board gets this long, plus some time for each swap of its par
+const BASE_TIME_MS: f64 = 15_000.0;
+const TIME_PER_SWAP_MS: f64 = 3_000.0;
+
+/// Time added whenever a tile is moved to where it belongs
+const PLACEMENT_BONUS_MS: f64 = 1_500.0;
+
+/// A run of ever harder boards played against the clock. The run is saved
+/// after every tick so reloading the page doesn't give any time back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeAttackRun {
+    pub levels_cleared: usize,
+    pub remaining_ms: f64,
+    /// The most tiles that have been in place at once on the current board,
+    /// only beating it earns bonus time so undoing and redoing doesn't
+    best_placed: usize,
+}
+
+/// Results of the finished time attack runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeAttackRecord {
+    pub runs: usize,
+    pub best_levels: usize,
+}
+
+impl TimeAttackRun {
+    pub fn new(puzzle: &Puzzle) -> Self {
+        Self {
+            levels_cleared: 0,
+            remaining_ms: board_time_ms(puzzle),
+            best_placed: puzzle.correctly_placed(),
+        }
+    }
+
+    pub fn load(key: &str) -> Option<Self> {
+        savegame::load(key)
+    }
+
+    pub fn save(&self, key: &str) {
+        savegame::save(key, self);
+    }
+
+    /// The difficulty of the board currently being played
+    pub fn difficulty(&self) -> usize {
+        self.levels_cleared + 1
+    }
+
+    /// Counts the solved board and starts the countdown for the next one
+    pub fn next_board(&mut self, puzzle: &Puzzle) {
+        self.levels_cleared += 1;
+        self.remaining_ms = board_time_ms(puzzle);
+        self.best_placed = puzzle.correctly_placed();
+    }
+
+    pub fn tick(&mut self, elapsed_ms: f64) {
+        self.remaining_ms = (self.remaining_ms - elapsed_ms.max(0.0)).max(0.0);
+    }
+
+    /// Adds the bonus for any tiles placed beyond the best so far
+    pub fn place_tiles(&mut self, placed: usize) {
+        if placed > self.best_placed && !self.is_expired() {
+            let bonus = (placed - self.best_placed) as f64 * PLACEMENT_BONUS_MS;
+            self.remaining_ms += bonus;
+            self.best_placed = placed;
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining_ms <= 0.0
+    }
+}
+
+impl TimeAttackRecord {
+    pub fn load(key: &str) -> Self {
+        savegame::load(key).unwrap_or_default()
+    }
+
+    pub fn save(&self, key: &str) {
+        savegame::save(key, self);
+    }
+
+    pub fn add_run(&mut self, run: &TimeAttackRun) {
+        self.runs += 1;
+        self.best_levels = self.best_levels.max(run.levels_cleared);
+    }
+}
+
+/// The countdown a board starts with, sized to the fewest swaps it takes
+fn board_time_ms(puzzle: &Puzzle) -> f64 {
+    BASE_TIME_MS + puzzle.par() as f64 * TIME_PER_SWAP_MS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient::{Color, Gradient};
+    use crate::puzzle::{LockingPattern, MoveKind, PuzzleSettings};
+
+    #[test]
+    fn countdown() {
+        let gradient = Gradient::builder()
+            .top_left(Color::rgb(255, 0, 0))
+            .top_right(Color::rgb(255, 255, 0))
+            .bottom_left(Color::rgb(0, 0, 255))
+            .bottom_right(Color::rgb(0, 255, 0))
+            .build();
+        let pattern = LockingPattern::Corners;
+        let settings = PuzzleSettings::custom(5, 5, gradient, pattern, MoveKind::Swaps).unwrap();
+        let puzzle = Puzzle::shuffled(settings);
+        let mut run = TimeAttackRun::new(&puzzle);
+        let start = run.remaining_ms;
+        assert_eq!(start, board_time_ms(&puzzle));
+        assert!(start > BASE_TIME_MS);
+
+        run.tick(1000.0);
+        assert_eq!(run.remaining_ms, start - 1000.0);
+
+        // only new bests earn bonus time
+        let placed = puzzle.correctly_placed();
+        run.place_tiles(placed + 2);
+        assert_eq!(run.remaining_ms, start - 1000.0 + 2.0 * PLACEMENT_BONUS_MS);
+        run.place_tiles(placed + 1);
+        run.place_tiles(placed + 2);
+        assert_eq!(run.remaining_ms, start - 1000.0 + 2.0 * PLACEMENT_BONUS_MS);
+
+        run.next_board(&puzzle);
+        assert_eq!((run.levels_cleared, run.difficulty()), (1, 2));
+        assert_eq!(run.remaining_ms, start);
+
+        run.tick(f64::INFINITY);
+        assert!(run.is_expired());
+        run.place_tiles(placed + 5);
+        assert!(run.is_expired());
+
+        let mut record = TimeAttackRecord::default();
+        record.add_run(&run);
+        assert_eq!((record.runs, record.best_levels), (1, 1));
+    }
+}
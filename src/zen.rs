@@ -0,0 +1,52 @@
+use crate::puzzle::{DifficultyProfile, Puzzle};
+use crate::savegame;
+use serde::{Deserialize, Serialize};
+
+pub const MIN_DIFFICULTY: usize = 1;
+pub const MAX_DIFFICULTY: usize = 200;
+
+/// The zen mode options the player picked, kept apart from the main
+/// progression
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZenSettings {
+    pub difficulty: usize,
+    /// Reuse the gradient of the solved board for the next one
+    pub keep_palette: bool,
+}
+
+impl Default for ZenSettings {
+    fn default() -> Self {
+        Self {
+            difficulty: 10,
+            keep_palette: false,
+        }
+    }
+}
+
+impl ZenSettings {
+    pub fn load(key: &str) -> Self {
+        savegame::load(key).unwrap_or_default()
+    }
+
+    pub fn save(&self, key: &str) {
+        savegame::save(key, self);
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: usize) {
+        self.difficulty = difficulty.clamp(MIN_DIFFICULTY, MAX_DIFFICULTY);
+    }
+
+    /// The board that follows the given one
+    pub fn next_puzzle(
+        &self,
+        current: &Puzzle,
+        profile: &DifficultyProfile,
+        win_size: (usize, usize),
+    ) -> Puzzle {
+        if self.keep_palette && current.settings.difficulty() == self.difficulty {
+            current.reshuffled()
+        } else {
+            Puzzle::generate(profile, self.difficulty, rand::random(), win_size)
+        }
+    }
+}